@@ -1,19 +1,259 @@
-use crate::config::types::{BrandConfig, Config};
+use crate::config::schema::SchemaFile;
+use crate::config::strict::{find_unknown_defaults, find_unknown_fields};
+use crate::config::types::{
+    BrandConfig, CONFIG_OVERLAY_FILE, Config, ConfigInclude, ConfigOverlay, EntryOrigin,
+    TransformationDefaults, TransformationEntry,
+};
 use crate::error::{Error, Result};
+use crate::utils::conditions::check_condition_expression;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt::Display;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
+/// Load `config.json`, appending the transformations of every file it
+/// `include`s. Include paths are relative to the directory holding
+/// `config.json`; each entry records the file it came from.
 pub fn load_config(config_path: &Path) -> Result<Config> {
     if !config_path.exists() {
         return Err(Error::FileNotFound(config_path.to_path_buf()));
     }
 
     let contents = fs::read_to_string(config_path)?;
-    let config: Config = serde_json::from_str(&contents)?;
+    let mut value: Value = serde_json::from_str(&contents)?;
+    let defaults = read_defaults(config_path, &value)?;
+    apply_defaults(&mut value, &defaults);
+    let mut config: Config = parse_strict(config_path, value, SchemaFile::Config)?;
+
+    let root_dir = config_path.parent().unwrap_or(Path::new(""));
+    let root_name = config_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    tag_origins(&mut config.transformations, &root_name);
+
+    let mut stack = vec![(fs::canonicalize(config_path)?, root_name)];
+    let mut loaded = HashMap::new();
+    let includes = std::mem::take(&mut config.include);
+    for include in &includes {
+        append_include(
+            root_dir,
+            include,
+            &defaults,
+            &mut stack,
+            &mut loaded,
+            &mut config.transformations,
+        )?;
+    }
+    config.include = includes;
+    check_entries(&config.transformations)?;
 
     Ok(config)
 }
 
+/// Append an included file's transformations, then those of the files it
+/// includes in turn. `stack` holds the files currently being loaded, to
+/// report include cycles, and `loaded` maps every file included so far to
+/// the file that included it, so a file can't be included twice.
+fn append_include(
+    root_dir: &Path,
+    include: &str,
+    defaults: &TransformationDefaults,
+    stack: &mut Vec<(PathBuf, String)>,
+    loaded: &mut HashMap<PathBuf, String>,
+    transformations: &mut Vec<TransformationEntry>,
+) -> Result<()> {
+    let path = root_dir.join(include);
+    if !path.exists() {
+        return Err(Error::FileNotFound(path));
+    }
+
+    let canonical = fs::canonicalize(&path)?;
+    if stack.iter().any(|(file, _)| *file == canonical) {
+        let mut names: Vec<&str> = stack.iter().map(|(_, name)| name.as_str()).collect();
+        names.push(include);
+        return Err(Error::Config(format!(
+            "Config include cycle: {}",
+            names.join(" -> ")
+        )));
+    }
+
+    let includer = stack.last().map_or("", |(_, name)| name.as_str());
+    if let Some(first_includer) = loaded.get(&canonical) {
+        return Err(Error::Config(format!(
+            "Config file '{}' is included more than once (by {} and {})",
+            include, first_includer, includer
+        )));
+    }
+    loaded.insert(canonical.clone(), includer.to_string());
+
+    let contents = fs::read_to_string(&path)?;
+    let mut value: Value = serde_json::from_str(&contents)?;
+    apply_defaults(&mut value, defaults);
+    let mut included: ConfigInclude = parse_strict(&path, value, SchemaFile::ConfigInclude)?;
+    tag_origins(&mut included.transformations, include);
+    transformations.append(&mut included.transformations);
+
+    stack.push((canonical, include.to_string()));
+    for nested in &included.include {
+        append_include(root_dir, nested, defaults, stack, loaded, transformations)?;
+    }
+    stack.pop();
+
+    Ok(())
+}
+
+/// Apply each brand's `config.overlay.json` to `config`, starting with the
+/// root of the `extends` chain so a brand can adjust what it inherits.
+/// Returns a description of every removal or patch that matched nothing.
+pub fn apply_brand_overlays(
+    config: &mut Config,
+    config_path: &Path,
+    source_dirs: &[PathBuf],
+) -> Result<Vec<String>> {
+    let root_dir = config_path.parent().unwrap_or(Path::new(""));
+    let mut unmatched = Vec::new();
+
+    for source_dir in source_dirs.iter().rev() {
+        let path = source_dir.join(CONFIG_OVERLAY_FILE);
+        if !path.exists() {
+            continue;
+        }
+
+        let contents = fs::read_to_string(&path)?;
+        let mut value: Value = serde_json::from_str(&contents)?;
+        apply_defaults(&mut value, &config.defaults);
+        let overlay: ConfigOverlay = parse_strict(&path, value, SchemaFile::ConfigOverlay)?;
+        let file = path
+            .strip_prefix(root_dir)
+            .unwrap_or(&path)
+            .display()
+            .to_string();
+        apply_overlay(config, overlay, &file, &mut unmatched)?;
+    }
+    check_entries(&config.transformations)?;
+
+    Ok(unmatched)
+}
+
+fn apply_overlay(
+    config: &mut Config,
+    overlay: ConfigOverlay,
+    file: &str,
+    unmatched: &mut Vec<String>,
+) -> Result<()> {
+    for output_path in &overlay.remove {
+        let before = config.transformations.len();
+        config
+            .transformations
+            .retain(|entry| !entry.transformation.output_paths().contains(output_path));
+        if config.transformations.len() == before {
+            unmatched.push(format!(
+                "{}: remove '{}' matched no transformation",
+                file, output_path
+            ));
+        }
+    }
+
+    for (index, patch) in overlay.patch.iter().enumerate() {
+        let mut matched = false;
+        for entry in config.transformations.iter_mut().filter(|entry| {
+            entry
+                .transformation
+                .output_paths()
+                .contains(&patch.output_path)
+        }) {
+            patch_entry(entry, &patch.set, file, index)?;
+            matched = true;
+        }
+        if !matched {
+            unmatched.push(format!(
+                "{}: patch '{}' matched no transformation",
+                file, patch.output_path
+            ));
+        }
+    }
+
+    let mut added = overlay.transformations;
+    tag_origins(&mut added, file);
+    config.transformations.append(&mut added);
+
+    Ok(())
+}
+
+/// Set `fields` on `entry`, checking the result against the config schema
+/// the same way `config.json` itself is checked.
+fn patch_entry(
+    entry: &mut TransformationEntry,
+    fields: &serde_json::Map<String, Value>,
+    file: &str,
+    index: usize,
+) -> Result<()> {
+    let mut value = serde_json::to_value(&*entry)?;
+    if let Value::Object(ref mut entry_fields) = value {
+        entry_fields.extend(fields.clone());
+    }
+
+    let wrapped = serde_json::json!({ "brandConfigPath": "", "transformations": [value] });
+    let schema: Value = serde_json::from_str(&SchemaFile::Config.to_json())?;
+    let unknown = find_unknown_fields(&wrapped, &schema);
+    if !unknown.is_empty() {
+        let details: Vec<String> = unknown
+            .into_iter()
+            .map(|mut field| {
+                field.path =
+                    field
+                        .path
+                        .replacen("transformations[0]", &format!("patch[{}].set", index), 1);
+                field.to_string()
+            })
+            .collect();
+        return Err(Error::Config(format!("{}: {}", file, details.join("; "))));
+    }
+
+    let origin = entry.origin.take();
+    *entry = deserialize_at(
+        wrapped["transformations"][0].clone(),
+        file,
+        &format!("patch[{}].set", index),
+    )?;
+    entry.origin = origin;
+    Ok(())
+}
+
+/// Reject entries the schema can't fully describe: `when` conditions that
+/// don't parse, rather than treating them as false for every brand, and
+/// rasters without exactly one of their two output forms.
+fn check_entries(transformations: &[TransformationEntry]) -> Result<()> {
+    for entry in transformations {
+        let with_origin = |e: Error, field: &str| match (e, &entry.origin) {
+            (Error::Config(msg), Some(origin)) => {
+                Error::Config(format!("{}: {}{}", origin, field, msg))
+            }
+            (e, _) => e,
+        };
+        if let Some(ref when) = entry.when {
+            check_condition_expression(when).map_err(|e| with_origin(e, "when: "))?;
+        }
+        entry
+            .transformation
+            .check_outputs()
+            .map_err(|e| with_origin(e, ""))?;
+    }
+    Ok(())
+}
+
+fn tag_origins(transformations: &mut [TransformationEntry], file: &str) {
+    for (index, entry) in transformations.iter_mut().enumerate() {
+        entry.origin = Some(EntryOrigin {
+            file: file.to_string(),
+            index,
+        });
+    }
+}
+
 pub fn load_brand_config(brand_config_path: &Path) -> Result<BrandConfig> {
     if !brand_config_path.exists() {
         // Brand config is optional
@@ -21,7 +261,589 @@ pub fn load_brand_config(brand_config_path: &Path) -> Result<BrandConfig> {
     }
 
     let contents = fs::read_to_string(brand_config_path)?;
-    let brand_config: BrandConfig = serde_json::from_str(&contents)?;
+    parse_strict(
+        brand_config_path,
+        serde_json::from_str(&contents)?,
+        SchemaFile::BrandConfig,
+    )
+}
 
-    Ok(brand_config)
+/// Read and check the `defaults` of a parsed config.json.
+fn read_defaults(config_path: &Path, value: &Value) -> Result<TransformationDefaults> {
+    let Some(defaults) = value.get("defaults") else {
+        return Ok(TransformationDefaults::new());
+    };
+
+    let schema: Value = serde_json::from_str(&SchemaFile::Config.to_json())?;
+    let unknown = find_unknown_defaults(defaults, &schema);
+    if !unknown.is_empty() {
+        let details: Vec<String> = unknown.iter().map(ToString::to_string).collect();
+        return Err(Error::Config(format!(
+            "{}: {}",
+            config_path.display(),
+            details.join("; ")
+        )));
+    }
+
+    deserialize_at(defaults.clone(), config_path.display(), "defaults")
+}
+
+/// Fill in fields each entry in a parsed config file's `transformations`
+/// leaves out from the defaults for its type. Done before deserializing, so
+/// errors describe the merged entry.
+fn apply_defaults(value: &mut Value, defaults: &TransformationDefaults) {
+    let Some(entries) = value
+        .get_mut("transformations")
+        .and_then(Value::as_array_mut)
+    else {
+        return;
+    };
+    for entry in entries.iter_mut().filter_map(Value::as_object_mut) {
+        let Some(fields) = entry
+            .get("type")
+            .and_then(Value::as_str)
+            .and_then(|transformation_type| defaults.get(transformation_type))
+        else {
+            continue;
+        };
+        for (key, default) in fields.clone() {
+            entry.entry(key).or_insert(default);
+        }
+    }
+}
+
+/// Deserialize a config file, rejecting keys its schema doesn't define
+/// instead of silently ignoring them.
+fn parse_strict<T: DeserializeOwned>(path: &Path, value: Value, schema: SchemaFile) -> Result<T> {
+    let schema: Value = serde_json::from_str(&schema.to_json())?;
+    let unknown = find_unknown_fields(&value, &schema);
+    if !unknown.is_empty() {
+        let details: Vec<String> = unknown.iter().map(ToString::to_string).collect();
+        return Err(Error::Config(format!(
+            "{}: {}",
+            path.display(),
+            details.join("; ")
+        )));
+    }
+
+    deserialize_at(value, path.display(), "")
+}
+
+/// Deserialize `value`, naming the file and the JSON path of a value that
+/// doesn't fit the same way unknown-field errors do. `prefix` is the path of
+/// `value` within the file.
+fn deserialize_at<T: DeserializeOwned>(
+    value: Value,
+    file: impl Display,
+    prefix: &str,
+) -> Result<T> {
+    serde_path_to_error::deserialize(value).map_err(|e| {
+        let path = match (prefix, e.path().to_string()) {
+            (prefix, path) if path == "." => prefix.to_string(),
+            ("", path) => path,
+            (prefix, path) if path.starts_with('[') => format!("{}{}", prefix, path),
+            (prefix, path) => format!("{}.{}", prefix, path),
+        };
+        if path.is_empty() {
+            Error::Config(format!("{}: {}", file, e.into_inner()))
+        } else {
+            Error::Config(format!("{}: {}: {}", file, path, e.into_inner()))
+        }
+    })
+}
+
+/// A brand config merged with every brand it `extends`.
+#[derive(Debug)]
+pub struct BrandChain {
+    pub brand_config: BrandConfig,
+    /// Source directories to resolve `source` files from, the brand itself first
+    pub source_dirs: Vec<PathBuf>,
+}
+
+/// Load the brand config in `source_dir`, following `extends` to sibling brand
+/// directories. Values set closer to the brand win over inherited ones.
+pub fn load_brand_chain(source_dir: &Path, brand_config_path: &str) -> Result<BrandChain> {
+    let mut source_dirs = vec![source_dir.to_path_buf()];
+    let mut configs = vec![load_brand_config(&source_dir.join(brand_config_path))?];
+
+    while let Some(parent) = configs.last().and_then(|config| config.extends.clone()) {
+        let current_dir = source_dirs.last().unwrap();
+        let parent_dir = current_dir
+            .parent()
+            .map(|brands_dir| brands_dir.join(&parent))
+            .filter(|dir| dir.is_dir())
+            .ok_or_else(|| {
+                Error::Config(format!(
+                    "Brand '{}' extends '{}', which does not exist",
+                    brand_name(current_dir),
+                    parent
+                ))
+            })?;
+
+        if source_dirs.contains(&parent_dir) {
+            let mut names: Vec<String> = source_dirs.iter().map(|dir| brand_name(dir)).collect();
+            names.push(parent);
+            return Err(Error::Config(format!(
+                "Brand inheritance cycle: {}",
+                names.join(" -> ")
+            )));
+        }
+
+        configs.push(load_brand_config(&parent_dir.join(brand_config_path))?);
+        source_dirs.push(parent_dir);
+    }
+
+    // Apply the root ancestor first so each child overrides what it inherits
+    let mut brand_config = BrandConfig::default();
+    for config in configs.into_iter().rev() {
+        // A child's plain string replaces any typed value it inherited
+        for key in config.strings.keys() {
+            if !config.data.contains_key(key) {
+                brand_config.data.remove(key);
+            }
+        }
+        brand_config.strings.extend(config.strings);
+        brand_config.env.extend(config.env);
+        brand_config.data.extend(config.data);
+        for (locale, strings) in config.locales {
+            brand_config
+                .locales
+                .entry(locale)
+                .or_default()
+                .extend(strings);
+        }
+    }
+
+    Ok(BrandChain {
+        brand_config,
+        source_dirs,
+    })
+}
+
+pub fn brand_name(source_dir: &Path) -> String {
+    source_dir
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp::TempDir;
+
+    fn write_brand(brands: &TempDir, name: &str, config: &str) -> PathBuf {
+        let dir = brands.create_dir(name).unwrap();
+        fs::write(dir.join("brand-config.json"), config).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_load_brand_chain() {
+        let brands = TempDir::new("brand-chain").unwrap();
+        let official = write_brand(
+            &brands,
+            "official",
+            r#"{ "env": { "name": "official" }, "strings": { "brandShortName": "Firefox", "dmgWindowSize": "680 425" } }"#,
+        );
+        let beta = write_brand(
+            &brands,
+            "beta",
+            r#"{ "extends": "official", "strings": { "brandShortName": "Firefox Beta" } }"#,
+        );
+        let nightly = write_brand(
+            &brands,
+            "nightly",
+            r#"{ "extends": "beta", "env": { "name": "nightly" } }"#,
+        );
+
+        let chain = load_brand_chain(&nightly, "brand-config.json").unwrap();
+        assert_eq!(chain.source_dirs, [nightly, beta, official]);
+        assert_eq!(chain.brand_config.env["name"], "nightly");
+        assert_eq!(chain.brand_config.strings["brandShortName"], "Firefox Beta");
+        assert_eq!(chain.brand_config.strings["dmgWindowSize"], "680 425");
+    }
+
+    #[test]
+    fn test_unknown_fields_are_rejected() {
+        let brands = TempDir::new("brand-chain").unwrap();
+        let typo = write_brand(
+            &brands,
+            "typo",
+            r#"{ "strigns": { "brandShortName": "Firefox" } }"#,
+        );
+
+        let err = load_brand_chain(&typo, "brand-config.json").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("unknown field 'strigns', did you mean 'strings'?"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_type_errors_name_their_location() {
+        let root = TempDir::new("config-types").unwrap();
+        let brand = write_brand(&root, "official", r#"{ "env": { "name": 5 } }"#);
+        let err = load_brand_chain(&brand, "brand-config.json").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("brand-config.json: env.name: invalid type: integer `5`"),
+            "{}",
+            err
+        );
+
+        fs::write(
+            root.join("config.json"),
+            r#"{ "brandConfigPath": "brand-config.json", "transformations": [
+                { "type": "copy", "fileType": "source", "inputPath": "a.ico", "outputPath": "a.ico" },
+                { "type": "raster", "fileType": "source", "inputPath": "a.svg", "outputPath": "a.png", "outputFileType": "png", "width": "big", "height": 16 }
+            ] }"#,
+        )
+        .unwrap();
+        let err = load_config(&root.join("config.json")).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("config.json: transformations[1]: invalid type: string \"big\""),
+            "{}",
+            err
+        );
+
+        // Rasters need exactly one of their output forms
+        for outputs in [
+            "",
+            r#", "outputPath": "a.png", "width": 16, "height": 16, "outputs": []"#,
+        ] {
+            fs::write(
+                root.join("config.json"),
+                format!(
+                    r#"{{ "brandConfigPath": "brand-config.json", "transformations": [
+                        {{ "type": "raster", "fileType": "source", "inputPath": "a.svg", "outputFileType": "png"{} }}
+                    ] }}"#,
+                    outputs
+                ),
+            )
+            .unwrap();
+            let err = load_config(&root.join("config.json")).unwrap_err();
+            assert!(
+                err.to_string()
+                    .contains("config.json transformations[0]: raster of 'a.svg': "),
+                "{}",
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn test_load_brand_chain_errors() {
+        let brands = TempDir::new("brand-chain").unwrap();
+        let a = write_brand(&brands, "a", r#"{ "extends": "b" }"#);
+        write_brand(&brands, "b", r#"{ "extends": "a" }"#);
+        let orphan = write_brand(&brands, "orphan", r#"{ "extends": "missing" }"#);
+
+        let err = load_brand_chain(&a, "brand-config.json").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Brand inheritance cycle: a -> b -> a"),
+            "{}",
+            err
+        );
+
+        let err = load_brand_chain(&orphan, "brand-config.json").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Brand 'orphan' extends 'missing', which does not exist"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_load_config_includes() {
+        let root = TempDir::new("config-include").unwrap();
+        root.create_dir("config").unwrap();
+        let copy = |output: &str| {
+            format!(
+                r#"{{ "type": "copy", "fileType": "source", "inputPath": "a", "outputPath": "{output}" }}"#
+            )
+        };
+        fs::write(
+            root.join("config.json"),
+            format!(
+                r#"{{ "brandConfigPath": "brand-config.json", "include": ["config/a.json", "config/c.json"], "transformations": [{}] }}"#,
+                copy("root")
+            ),
+        )
+        .unwrap();
+        fs::write(
+            root.join("config/a.json"),
+            format!(
+                r#"{{ "include": ["config/b.json"], "transformations": [{}, {}] }}"#,
+                copy("a0"),
+                copy("a1")
+            ),
+        )
+        .unwrap();
+        fs::write(
+            root.join("config/b.json"),
+            format!(r#"{{ "transformations": [{}] }}"#, copy("b0")),
+        )
+        .unwrap();
+        fs::write(
+            root.join("config/c.json"),
+            format!(r#"{{ "transformations": [{}] }}"#, copy("c0")),
+        )
+        .unwrap();
+
+        let config = load_config(&root.join("config.json")).unwrap();
+        let outputs: Vec<&str> = config
+            .transformations
+            .iter()
+            .map(|entry| entry.transformation.output_path())
+            .collect();
+        assert_eq!(outputs, ["root", "a0", "a1", "b0", "c0"]);
+
+        let origins: Vec<String> = config
+            .transformations
+            .iter()
+            .map(|entry| entry.origin.as_ref().unwrap().to_string())
+            .collect();
+        assert_eq!(
+            origins,
+            [
+                "config.json transformations[0]",
+                "config/a.json transformations[0]",
+                "config/a.json transformations[1]",
+                "config/b.json transformations[0]",
+                "config/c.json transformations[0]",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_load_config_include_errors() {
+        let root = TempDir::new("config-include").unwrap();
+        fs::write(
+            root.join("config.json"),
+            r#"{ "brandConfigPath": "brand-config.json", "include": ["a.json"], "transformations": [] }"#,
+        )
+        .unwrap();
+        fs::write(root.join("a.json"), r#"{ "include": ["config.json"] }"#).unwrap();
+
+        let err = load_config(&root.join("config.json")).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Config include cycle: config.json -> a.json -> config.json"),
+            "{}",
+            err
+        );
+
+        fs::write(
+            root.join("a.json"),
+            r#"{ "include": ["b.json", "c.json"] }"#,
+        )
+        .unwrap();
+        fs::write(root.join("b.json"), r#"{ "include": ["d.json"] }"#).unwrap();
+        fs::write(root.join("c.json"), r#"{ "include": ["d.json"] }"#).unwrap();
+        fs::write(root.join("d.json"), r#"{ "transformations": [] }"#).unwrap();
+        let err = load_config(&root.join("config.json")).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Config file 'd.json' is included more than once (by b.json and c.json)"),
+            "{}",
+            err
+        );
+
+        fs::write(root.join("a.json"), r#"{ "include": ["missing.json"] }"#).unwrap();
+        let err = load_config(&root.join("config.json")).unwrap_err();
+        assert!(matches!(err, Error::FileNotFound(_)), "{}", err);
+
+        fs::write(
+            root.join("a.json"),
+            r#"{ "transformations": [{ "type": "copy", "fileType": "source", "inputPath": "a", "outputPath": "a", "when": "name = b" }] }"#,
+        )
+        .unwrap();
+        let err = load_config(&root.join("config.json")).unwrap_err();
+        assert!(
+            err.to_string().contains(
+                "a.json transformations[0]: when: Invalid condition expression: 'name = b'"
+            ),
+            "{}",
+            err
+        );
+
+        // Non-ASCII conditions load, or fail as configuration errors
+        let write_when = |when: &str| {
+            let entry = serde_json::json!({
+                "transformations": [{ "type": "copy", "fileType": "source", "inputPath": "a", "outputPath": "a", "when": when }]
+            });
+            fs::write(root.join("a.json"), entry.to_string()).unwrap();
+        };
+        write_when("strings.brandShortName == \"Développeur\" || name == beta");
+        assert!(load_config(&root.join("config.json")).is_ok());
+        write_when("strings.brandShortName = \"Développeur\" || name == beta");
+        let err = load_config(&root.join("config.json")).unwrap_err();
+        assert!(
+            matches!(err, Error::Config(ref msg) if msg.starts_with("a.json transformations[0]: when:")),
+            "{}",
+            err
+        );
+
+        fs::write(root.join("a.json"), r#"{ "brandConfigPath": "x" }"#).unwrap();
+        let err = load_config(&root.join("config.json")).unwrap_err();
+        assert!(
+            err.to_string().contains("unknown field 'brandConfigPath'"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_apply_brand_overlays() {
+        let root = TempDir::new("config-overlay").unwrap();
+        fs::write(
+            root.join("config.json"),
+            r#"{ "brandConfigPath": "brand-config.json", "transformations": [
+                { "type": "copy", "fileType": "source", "inputPath": "a.ico", "outputPath": "a.ico" },
+                { "type": "raster", "fileType": "source", "inputPath": "a.svg", "outputPath": "a.png", "outputFileType": "png", "width": 16, "height": 16 }
+            ] }"#,
+        )
+        .unwrap();
+        let official = write_brand(&root, "official", "{}");
+        let nightly = write_brand(&root, "nightly", r#"{ "extends": "official" }"#);
+        fs::write(
+            official.join(CONFIG_OVERLAY_FILE),
+            r#"{ "patch": [{ "outputPath": "a.png", "set": { "width": 32 } }] }"#,
+        )
+        .unwrap();
+        fs::write(
+            nightly.join(CONFIG_OVERLAY_FILE),
+            r#"{
+                "remove": ["a.ico", "gone.png"],
+                "patch": [{ "outputPath": "a.png", "set": { "height": 48 } }],
+                "transformations": [{ "type": "copy", "fileType": "source", "inputPath": "b.ico", "outputPath": "b.ico" }]
+            }"#,
+        )
+        .unwrap();
+
+        let config_path = root.join("config.json");
+        let mut config = load_config(&config_path).unwrap();
+        let unmatched =
+            apply_brand_overlays(&mut config, &config_path, &[nightly.clone(), official]).unwrap();
+        assert_eq!(
+            unmatched,
+            ["nightly/config.overlay.json: remove 'gone.png' matched no transformation"]
+        );
+
+        let outputs: Vec<&str> = config
+            .transformations
+            .iter()
+            .map(|entry| entry.transformation.output_path())
+            .collect();
+        assert_eq!(outputs, ["a.png", "b.ico"]);
+        match &config.transformations[0].transformation {
+            crate::config::Transformation::Raster { width, height, .. } => {
+                assert_eq!((*width, *height), (Some(32), Some(48)));
+            }
+            other => panic!("unexpected transformation {:?}", other),
+        }
+        assert_eq!(
+            config.transformations[1]
+                .origin
+                .as_ref()
+                .unwrap()
+                .to_string(),
+            "nightly/config.overlay.json transformations[0]"
+        );
+
+        fs::write(
+            nightly.join(CONFIG_OVERLAY_FILE),
+            r#"{ "patch": [{ "outputPath": "a.png", "set": { "widht": 32 } }] }"#,
+        )
+        .unwrap();
+        let mut config = load_config(&config_path).unwrap();
+        let err = apply_brand_overlays(&mut config, &config_path, std::slice::from_ref(&nightly))
+            .unwrap_err();
+        assert!(
+            err.to_string().contains(
+                "unknown field 'patch[0].set.widht' in raster transformation, did you mean 'width'?"
+            ),
+            "{}",
+            err
+        );
+
+        // Outputs added by `scales` match too
+        fs::write(
+            root.join("config.json"),
+            r#"{ "brandConfigPath": "brand-config.json", "transformations": [
+                { "type": "raster", "fileType": "source", "inputPath": "a.svg", "outputPath": "a.png", "outputFileType": "png", "width": 16, "height": 16, "scales": [1, 2] }
+            ] }"#,
+        )
+        .unwrap();
+        fs::write(
+            nightly.join(CONFIG_OVERLAY_FILE),
+            r#"{ "remove": ["a@2x.png"] }"#,
+        )
+        .unwrap();
+        let mut config = load_config(&config_path).unwrap();
+        let unmatched = apply_brand_overlays(&mut config, &config_path, &[nightly]).unwrap();
+        assert!(unmatched.is_empty(), "{:?}", unmatched);
+        assert!(config.transformations.is_empty());
+    }
+
+    #[test]
+    fn test_load_config_defaults() {
+        let root = TempDir::new("config-defaults").unwrap();
+        fs::write(
+            root.join("config.json"),
+            r#"{
+                "brandConfigPath": "brand-config.json",
+                "include": ["more.json"],
+                "defaults": { "raster": { "fileType": "source", "outputFileType": "png", "fit": "cover" } },
+                "transformations": [
+                    { "type": "raster", "inputPath": "a.svg", "outputPath": "a.png", "width": 16, "height": 16 },
+                    { "type": "raster", "fileType": "static", "inputPath": "b.svg", "outputPath": "b.png", "width": 16, "height": 16, "fit": "fill" }
+                ]
+            }"#,
+        )
+        .unwrap();
+        fs::write(
+            root.join("more.json"),
+            r#"{ "transformations": [{ "type": "raster", "inputPath": "c.svg", "outputPath": "c.png", "width": 16, "height": 16 }] }"#,
+        )
+        .unwrap();
+
+        let config = load_config(&root.join("config.json")).unwrap();
+        let rasters: Vec<String> = config
+            .transformations
+            .iter()
+            .map(|entry| match &entry.transformation {
+                crate::config::Transformation::Raster { file_type, fit, .. } => {
+                    format!("{:?} {:?}", file_type, fit)
+                }
+                other => panic!("unexpected transformation {:?}", other),
+            })
+            .collect();
+        assert_eq!(rasters, ["Source Cover", "Static Fill", "Source Cover"]);
+
+        fs::write(
+            root.join("config.json"),
+            r#"{
+                "brandConfigPath": "brand-config.json",
+                "defaults": { "rastr": {}, "raster": { "fitt": "cover" } },
+                "transformations": []
+            }"#,
+        )
+        .unwrap();
+        let err = load_config(&root.join("config.json")).unwrap_err();
+        assert!(
+            err.to_string().contains(
+                "unknown field 'defaults.raster.fitt' in raster transformation, did you mean 'fit'?; \
+                 unknown field 'defaults.rastr', did you mean 'raster'?"
+            ),
+            "{}",
+            err
+        );
+    }
 }
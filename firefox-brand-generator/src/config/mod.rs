@@ -1,5 +1,19 @@
 pub mod loader;
+pub mod overrides;
+pub mod schema;
+pub mod strict;
 pub mod types;
+pub mod virtual_brand;
 
-pub use loader::{load_brand_config, load_config};
-pub use types::{BrandConfig, Config, FileType, FitStrategy, OutputFileType, Transformation, TransformationEntry};
+pub use loader::{
+    BrandChain, apply_brand_overlays, load_brand_chain, load_brand_config, load_config,
+};
+pub use overrides::{BrandOverride, OverrideSection};
+pub use schema::SchemaFile;
+pub use types::{
+    BrandConfig, CONFIG_OVERLAY_FILE, Config, ConfigInclude, ConfigOverlay, EntryOrigin,
+    EscapeMode, FileType, FitStrategy, LineEndings, OutputEncoding, OutputFileType,
+    PreprocessDialect, RasterOutput, Transformation, TransformationDefaults, TransformationEntry,
+    TransformationPatch,
+};
+pub use virtual_brand::{VirtualAsset, VirtualBrand};
@@ -0,0 +1,156 @@
+use crate::config::types::BrandConfig;
+use crate::error::{Error, Result};
+use std::fmt;
+
+/// Prefix of environment variables that override brand strings,
+/// e.g. `FXBRAND_STRING_brandShortName`.
+pub const STRING_ENV_PREFIX: &str = "FXBRAND_STRING_";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverrideSection {
+    Strings,
+    Env,
+}
+
+/// A single brand value set from outside `brand-config.json`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrandOverride {
+    pub section: OverrideSection,
+    pub key: String,
+    pub value: String,
+    /// Where the override came from, for reporting (`--set` or the variable name)
+    pub origin: String,
+}
+
+impl BrandOverride {
+    /// Parse a `--set` argument of the form `strings.<key>=<value>` or `env.<key>=<value>`.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let invalid = || {
+            Error::Config(format!(
+                "Invalid --set '{}': expected strings.<key>=<value> or env.<key>=<value>",
+                spec
+            ))
+        };
+
+        let (path, value) = spec.split_once('=').ok_or_else(invalid)?;
+        let (section, key) = match path.trim().split_once('.') {
+            Some(("strings", key)) => (OverrideSection::Strings, key),
+            Some(("env", key)) => (OverrideSection::Env, key),
+            _ => return Err(invalid()),
+        };
+        if key.is_empty() {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            section,
+            key: key.to_string(),
+            value: value.to_string(),
+            origin: "--set".to_string(),
+        })
+    }
+
+    /// Overrides from `FXBRAND_STRING_<key>` variables, sorted by key.
+    pub fn from_env_vars(vars: impl IntoIterator<Item = (String, String)>) -> Vec<Self> {
+        let mut overrides: Vec<Self> = vars
+            .into_iter()
+            .filter_map(|(name, value)| {
+                let key = name.strip_prefix(STRING_ENV_PREFIX)?;
+                (!key.is_empty()).then(|| Self {
+                    section: OverrideSection::Strings,
+                    key: key.to_string(),
+                    value,
+                    origin: name.clone(),
+                })
+            })
+            .collect();
+        overrides.sort_by(|a, b| a.key.cmp(&b.key));
+        overrides
+    }
+
+    pub fn apply(&self, brand_config: &mut BrandConfig) {
+        match self.section {
+            OverrideSection::Strings => {
+                // The override is plain text, so drop any typed value it replaces
+                brand_config.data.remove(&self.key);
+                brand_config
+                    .strings
+                    .insert(self.key.clone(), self.value.clone());
+            }
+            OverrideSection::Env => {
+                brand_config
+                    .env
+                    .insert(self.key.clone(), self.value.clone());
+            }
+        }
+    }
+}
+
+impl fmt::Display for BrandOverride {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let section = match self.section {
+            OverrideSection::Strings => "strings",
+            OverrideSection::Env => "env",
+        };
+        write!(
+            f,
+            "{}.{} = {:?} (from {})",
+            section, self.key, self.value, self.origin
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_override() {
+        let parsed = BrandOverride::parse("strings.brandShortName=Firefox PR 123").unwrap();
+        assert_eq!(parsed.section, OverrideSection::Strings);
+        assert_eq!(parsed.key, "brandShortName");
+        assert_eq!(parsed.value, "Firefox PR 123");
+
+        let parsed = BrandOverride::parse("env.name=a=b").unwrap();
+        assert_eq!(parsed.section, OverrideSection::Env);
+        assert_eq!(parsed.value, "a=b");
+
+        for invalid in ["brandShortName=x", "strings.=x", "data.x=1", "strings.name"] {
+            assert!(BrandOverride::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_apply_overrides() {
+        let mut brand_config: BrandConfig = serde_json::from_value(serde_json::json!({
+            "env": { "name": "official" },
+            "strings": { "brandShortName": "Firefox", "dmgWindowSize": [680, 425] }
+        }))
+        .unwrap();
+
+        let vars = [
+            (
+                "FXBRAND_STRING_dmgWindowSize".to_string(),
+                "700 400".to_string(),
+            ),
+            ("FXBRAND_STRING_".to_string(), "ignored".to_string()),
+            ("HOME".to_string(), "/root".to_string()),
+        ];
+        let mut overrides = BrandOverride::from_env_vars(vars);
+        assert_eq!(overrides.len(), 1);
+        overrides.push(BrandOverride::parse("strings.brandShortName=Firefox PR 123").unwrap());
+        overrides.push(BrandOverride::parse("env.name=nightly").unwrap());
+
+        for o in &overrides {
+            o.apply(&mut brand_config);
+        }
+        assert_eq!(brand_config.strings["brandShortName"], "Firefox PR 123");
+        assert_eq!(brand_config.strings["dmgWindowSize"], "700 400");
+        assert!(!brand_config.data.contains_key("dmgWindowSize"));
+        assert_eq!(brand_config.env["name"], "nightly");
+        assert_eq!(
+            overrides[0].to_string(),
+            "strings.dmgWindowSize = \"700 400\" (from FXBRAND_STRING_dmgWindowSize)"
+        );
+    }
+}
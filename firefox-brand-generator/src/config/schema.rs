@@ -0,0 +1,89 @@
+use crate::config::types::{BrandConfig, Config, ConfigInclude, ConfigOverlay};
+use schemars::schema_for;
+
+/// A configuration file that has a JSON Schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaFile {
+    /// `config.json` at the repo root
+    Config,
+    /// A file listed in `config.json`'s `include`
+    ConfigInclude,
+    /// `config.overlay.json` in a brand folder
+    ConfigOverlay,
+    /// `brand-config.json` in each brand folder
+    BrandConfig,
+}
+
+impl SchemaFile {
+    /// The schema as pretty-printed JSON, generated from the serde definitions.
+    pub fn to_json(self) -> String {
+        let schema = match self {
+            SchemaFile::Config => schema_for!(Config),
+            SchemaFile::ConfigInclude => schema_for!(ConfigInclude),
+            SchemaFile::ConfigOverlay => schema_for!(ConfigOverlay),
+            SchemaFile::BrandConfig => schema_for!(BrandConfig),
+        };
+        serde_json::to_string_pretty(&schema).expect("schema serializes to JSON")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The committed schemas are what editors load; regenerate them with
+    // `firefox-brand-generator schema config > schemas/config.schema.json`
+    // (and likewise for the other schema files) when the config types change.
+    #[test]
+    fn test_committed_schemas_are_current() {
+        let committed = [
+            (
+                SchemaFile::Config,
+                include_str!("../../../schemas/config.schema.json"),
+            ),
+            (
+                SchemaFile::ConfigInclude,
+                include_str!("../../../schemas/config-include.schema.json"),
+            ),
+            (
+                SchemaFile::ConfigOverlay,
+                include_str!("../../../schemas/config-overlay.schema.json"),
+            ),
+            (
+                SchemaFile::BrandConfig,
+                include_str!("../../../schemas/brand-config.schema.json"),
+            ),
+        ];
+        for (file, contents) in committed {
+            assert_eq!(
+                contents.trim_end(),
+                file.to_json(),
+                "schemas/ is out of date for {:?}",
+                file
+            );
+        }
+    }
+
+    #[test]
+    fn test_schema_covers_every_transformation() {
+        let schema: serde_json::Value =
+            serde_json::from_str(&SchemaFile::Config.to_json()).unwrap();
+        let schema_text = schema.to_string();
+        for transformation_type in [
+            "raster",
+            "ico",
+            "icns",
+            "assets-car",
+            "copy",
+            "copy-preprocess",
+            "copy-image-mac",
+            "ds-store",
+        ] {
+            assert!(
+                schema_text.contains(&format!("\"const\":\"{}\"", transformation_type)),
+                "schema has no '{}' transformation",
+                transformation_type
+            );
+        }
+    }
+}
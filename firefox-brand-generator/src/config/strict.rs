@@ -0,0 +1,290 @@
+use serde_json::{Map, Value};
+
+/// A key in a config file that the config types don't define.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownField {
+    /// JSON path of the key, e.g. `transformations[3].offsetx`
+    pub path: String,
+    /// `type` of the transformation the key belongs to, if any
+    pub transformation_type: Option<String>,
+    /// Closest valid key at the same place
+    pub suggestion: Option<String>,
+}
+
+impl std::fmt::Display for UnknownField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown field '{}'", self.path)?;
+        if let Some(ref transformation_type) = self.transformation_type {
+            write!(f, " in {} transformation", transformation_type)?;
+        }
+        if let Some(ref suggestion) = self.suggestion {
+            write!(f, ", did you mean '{}'?", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+/// Find every key in `value` that `schema` doesn't allow.
+///
+/// Objects with `properties` and no `additionalProperties` are closed: the
+/// serde structs behind them would silently drop anything else. Maps
+/// (`additionalProperties`) are open. For tagged enums, only the branch whose
+/// `type` matches the value contributes fields. A top-level `$schema` key is
+/// always allowed so editors can find the schema.
+pub fn find_unknown_fields(value: &Value, schema: &Value) -> Vec<UnknownField> {
+    let mut unknown = Vec::new();
+    let mut allowed_root = Map::new();
+    allowed_root.insert("$schema".to_string(), Value::Bool(true));
+    walk(value, schema, schema, "", None, &allowed_root, &mut unknown);
+    unknown
+}
+
+/// Find every unknown key in config.json's `defaults`: types that aren't
+/// transformation types, and fields the named type doesn't accept.
+pub fn find_unknown_defaults(defaults: &Value, schema: &Value) -> Vec<UnknownField> {
+    let mut unknown = Vec::new();
+    let Some(defaults) = defaults.as_object() else {
+        return unknown;
+    };
+
+    let entry_schema = schema
+        .pointer("/$defs/TransformationEntry")
+        .unwrap_or(&Value::Null);
+    let types: Vec<String> = entry_schema
+        .get("oneOf")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|branch| {
+            resolve(branch, schema)
+                .pointer("/properties/type/const")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        })
+        .collect();
+
+    for (transformation_type, fields) in defaults {
+        let path = format!("defaults.{}", transformation_type);
+        if !types.contains(transformation_type) {
+            unknown.push(UnknownField {
+                path,
+                transformation_type: None,
+                suggestion: closest(transformation_type, types.iter()),
+            });
+            continue;
+        }
+
+        // Check the fields as if they were written on an entry of that type
+        let mut entry = fields.as_object().cloned().unwrap_or_default();
+        entry.insert(
+            "type".to_string(),
+            Value::String(transformation_type.clone()),
+        );
+        walk(
+            &Value::Object(entry),
+            entry_schema,
+            schema,
+            &path,
+            Some(transformation_type),
+            &Map::new(),
+            &mut unknown,
+        );
+    }
+    unknown
+}
+
+fn walk(
+    value: &Value,
+    schema: &Value,
+    root: &Value,
+    path: &str,
+    transformation_type: Option<&str>,
+    extra_allowed: &Map<String, Value>,
+    unknown: &mut Vec<UnknownField>,
+) {
+    let schema = resolve(schema, root);
+
+    match value {
+        Value::Object(fields) => {
+            let mut properties = schema
+                .get("properties")
+                .and_then(Value::as_object)
+                .cloned()
+                .unwrap_or_default();
+
+            // A tagged enum branch is picked by its `type` constant
+            let tag = fields.get("type").and_then(Value::as_str);
+            if let Some(branches) = schema.get("oneOf").and_then(Value::as_array) {
+                let branch = branches.iter().map(|b| resolve(b, root)).find(|branch| {
+                    branch
+                        .pointer("/properties/type/const")
+                        .and_then(Value::as_str)
+                        == tag
+                });
+                if let Some(branch_properties) = branch
+                    .and_then(|b| b.get("properties"))
+                    .and_then(Value::as_object)
+                {
+                    properties.extend(branch_properties.clone());
+                }
+            }
+            let transformation_type = if properties.contains_key("type") {
+                tag.or(transformation_type)
+            } else {
+                transformation_type
+            };
+
+            let additional = schema.get("additionalProperties");
+            for (key, field) in fields {
+                let field_path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+
+                if let Some(field_schema) = properties.get(key) {
+                    walk(
+                        field,
+                        field_schema,
+                        root,
+                        &field_path,
+                        transformation_type,
+                        &Map::new(),
+                        unknown,
+                    );
+                } else if let Some(additional) = additional {
+                    walk(
+                        field,
+                        additional,
+                        root,
+                        &field_path,
+                        transformation_type,
+                        &Map::new(),
+                        unknown,
+                    );
+                } else if !properties.is_empty() && !extra_allowed.contains_key(key) {
+                    unknown.push(UnknownField {
+                        path: field_path,
+                        transformation_type: transformation_type.map(str::to_string),
+                        suggestion: closest(key, properties.keys()),
+                    });
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for (index, item) in items.iter().enumerate() {
+                    walk(
+                        item,
+                        item_schema,
+                        root,
+                        &format!("{}[{}]", path, index),
+                        transformation_type,
+                        &Map::new(),
+                        unknown,
+                    );
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Follow a local `$ref` such as `#/$defs/TransformationEntry`.
+fn resolve<'a>(schema: &'a Value, root: &'a Value) -> &'a Value {
+    match schema.get("$ref").and_then(Value::as_str) {
+        Some(reference) => reference
+            .strip_prefix('#')
+            .and_then(|pointer| root.pointer(pointer))
+            .unwrap_or(schema),
+        None => schema,
+    }
+}
+
+/// The valid key most similar to `key`, if any is close enough to be a likely typo.
+fn closest<'a>(key: &str, candidates: impl Iterator<Item = &'a String>) -> Option<String> {
+    let key = key.to_lowercase();
+    candidates
+        .map(|candidate| {
+            let score = strsim::jaro_winkler(&key, &candidate.to_lowercase());
+            (score, candidate)
+        })
+        .filter(|(score, _)| *score >= 0.8)
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, candidate)| candidate.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SchemaFile;
+
+    fn schema(file: SchemaFile) -> Value {
+        serde_json::from_str(&file.to_json()).unwrap()
+    }
+
+    #[test]
+    fn test_unknown_transformation_fields() {
+        let config = serde_json::json!({
+            "$schema": "./schemas/config.schema.json",
+            "brandConfigPath": "brand-config.json",
+            "transformations": [
+                { "type": "copy", "fileType": "static", "inputPath": "a", "outputPath": "a" },
+                {
+                    "type": "raster",
+                    "fileType": "source",
+                    "inputPath": "logo.svg",
+                    "outputPath": "logo.png",
+                    "outputFileType": "png",
+                    "width": 16,
+                    "height": 16,
+                    "paddingPixelWidth": 2,
+                    "offsetx": 1,
+                    "only": ["official"]
+                }
+            ]
+        });
+
+        let unknown = find_unknown_fields(&config, &schema(SchemaFile::Config));
+        let messages: Vec<String> = unknown.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            messages,
+            [
+                "unknown field 'transformations[1].offsetx' in raster transformation, did you mean 'offsetX'?",
+                "unknown field 'transformations[1].paddingPixelWidth' in raster transformation, did you mean 'paddingPixelsWidth'?",
+            ]
+        );
+
+        // Fields of another transformation type aren't valid here
+        let config = serde_json::json!({
+            "brandConfigPath": "brand-config.json",
+            "transformations": [
+                { "type": "copy", "fileType": "static", "inputPath": "a", "outputPath": "a", "sizes": [16] }
+            ]
+        });
+        let unknown = find_unknown_fields(&config, &schema(SchemaFile::Config));
+        assert_eq!(unknown.len(), 1);
+        assert_eq!(unknown[0].path, "transformations[0].sizes");
+    }
+
+    #[test]
+    fn test_unknown_brand_config_fields() {
+        let brand_config = serde_json::json!({
+            "string": { "brandShortName": "Firefox" },
+            "env": { "anything": "goes" },
+            "data": { "tiles": [{ "free": "form" }] },
+            "locales": { "de": { "brandShortName": "Firefox" } },
+            "completelyDifferent": true
+        });
+
+        let unknown = find_unknown_fields(&brand_config, &schema(SchemaFile::BrandConfig));
+        let messages: Vec<String> = unknown.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            messages,
+            [
+                "unknown field 'completelyDifferent'",
+                "unknown field 'string', did you mean 'strings'?",
+            ]
+        );
+    }
+}
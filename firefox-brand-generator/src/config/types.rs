@@ -1,14 +1,70 @@
+use crate::transformations::scaled_output_path;
+use crate::utils::conditions::evaluate_condition_expression;
+use schemars::{JsonSchema, Schema, SchemaGenerator, json_schema};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde_json::Value;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
-#[serde(rename_all = "kebab-case")]
+/// Where an input file is read from: `"source"`, `"static"`, or
+/// `"brand:<name>"` for another brand's source directory.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
 pub enum FileType {
     Source,
     Static,
+    Brand(String),
+}
+
+impl TryFrom<String> for FileType {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "source" => Ok(FileType::Source),
+            "static" => Ok(FileType::Static),
+            _ => match value.strip_prefix("brand:") {
+                Some(name) if is_brand_name(name) => Ok(FileType::Brand(name.to_string())),
+                Some(name) => Err(format!("invalid brand name '{}' in fileType", name)),
+                None => Err(format!(
+                    "unknown fileType '{}', expected \"source\", \"static\" or \"brand:<name>\"",
+                    value
+                )),
+            },
+        }
+    }
+}
+
+impl From<FileType> for String {
+    fn from(file_type: FileType) -> Self {
+        match file_type {
+            FileType::Source => "source".to_string(),
+            FileType::Static => "static".to_string(),
+            FileType::Brand(name) => format!("brand:{}", name),
+        }
+    }
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+impl JsonSchema for FileType {
+    fn schema_name() -> Cow<'static, str> {
+        "FileType".into()
+    }
+
+    fn json_schema(_generator: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "description": "Where an input file is read from: \"source\" (the brand folder), \"static\" (the shared static/ folder) or \"brand:<name>\" (another brand's folder).",
+            "type": "string",
+            "pattern": "^(source|static|brand:[^/\\\\]+)$"
+        })
+    }
+}
+
+/// A single directory name under `brands/`, so `brand:` can't escape it.
+fn is_brand_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\\'])
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "kebab-case")]
 pub enum OutputFileType {
     Png,
@@ -18,35 +74,88 @@ pub enum OutputFileType {
     Gif,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "kebab-case")]
 pub enum FitStrategy {
     Fill,
     Cover,
+    #[default]
     Contain,
     ScaleDown,
 }
 
-impl Default for FitStrategy {
-    fn default() -> Self {
-        Self::Contain
-    }
+/// Escape applied to `{{#str}}` values that don't name one of their own.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum EscapeMode {
+    /// Pick the escape from the output file extension
+    #[default]
+    Auto,
+    None,
+    Xml,
+    Nsis,
+    Properties,
+    Fluent,
+    Json,
+}
+
+/// Directive syntax understood by `copy-preprocess`.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum PreprocessDialect {
+    /// This tool's `{{#if}}`/`{{#str}}` templates
+    #[default]
+    Template,
+    /// mozilla-central's `preprocessor.py` directives (`#ifdef`, `@VAR@`, ...)
+    Mozilla,
+}
+
+/// Character encoding of a `copy-preprocess` output file.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, JsonSchema)]
+pub enum OutputEncoding {
+    #[default]
+    #[serde(rename = "utf-8")]
+    Utf8,
+    #[serde(rename = "utf-8-bom")]
+    Utf8Bom,
+    /// Little-endian UTF-16, always written with a byte order mark
+    #[serde(rename = "utf-16le")]
+    Utf16Le,
+}
+
+/// Line endings of a `copy-preprocess` output file.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum LineEndings {
+    Lf,
+    Crlf,
+    /// Keep whatever the input file uses
+    #[default]
+    Preserve,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 #[serde(tag = "type", rename_all = "kebab-case")]
 pub enum Transformation {
+    /// Converts vector graphics or images to raster formats
+    #[schemars(extend("oneOf" = [
+        { "required": ["outputPath", "width", "height"] },
+        { "required": ["outputs"] }
+    ]))]
     Raster {
         #[serde(rename = "fileType")]
         file_type: FileType,
         #[serde(rename = "inputPath")]
         input_path: String,
+        /// Required unless `outputs` is given
         #[serde(rename = "outputPath")]
-        output_path: String,
+        output_path: Option<String>,
         #[serde(rename = "outputFileType")]
         output_file_type: OutputFileType,
-        width: u32,
-        height: u32,
+        /// Required unless `outputs` is given
+        width: Option<u32>,
+        /// Required unless `outputs` is given
+        height: Option<u32>,
         #[serde(rename = "paddingPixelsWidth")]
         padding_pixels_width: Option<u32>,
         #[serde(rename = "paddingPixelsHeight")]
@@ -57,7 +166,15 @@ pub enum Transformation {
         offset_y: Option<i32>,
         #[serde(default)]
         fit: FitStrategy,
+        /// Render once per scale, adding `@Nx` before the extension for
+        /// scales other than 1 and multiplying the sizes, padding and offsets
+        scales: Option<Vec<u32>>,
+        /// Render several sizes from the one input instead of a single
+        /// `outputPath`; padding and offsets on the entry apply to every
+        /// output that doesn't set its own
+        outputs: Option<Vec<RasterOutput>>,
     },
+    /// Creates Windows ICO files with multiple sizes
     Ico {
         #[serde(rename = "fileType")]
         file_type: FileType,
@@ -67,6 +184,7 @@ pub enum Transformation {
         output_path: String,
         sizes: Vec<u32>,
     },
+    /// Creates macOS ICNS files with multiple sizes
     Icns {
         #[serde(rename = "fileType")]
         file_type: FileType,
@@ -76,6 +194,7 @@ pub enum Transformation {
         output_path: String,
         sizes: Vec<u32>,
     },
+    /// Creates macOS Assets.car bundle
     AssetsCar {
         #[serde(rename = "liquidGlassIconFileType")]
         liquid_glass_icon_file_type: FileType,
@@ -92,6 +211,7 @@ pub enum Transformation {
         #[serde(rename = "iconFileType")]
         icon_file_type: FileType,
     },
+    /// Direct file copy without modification
     Copy {
         #[serde(rename = "fileType")]
         file_type: FileType,
@@ -100,6 +220,7 @@ pub enum Transformation {
         #[serde(rename = "outputPath")]
         output_path: String,
     },
+    /// File copy with template variable substitution
     CopyPreprocess {
         #[serde(rename = "fileType")]
         file_type: FileType,
@@ -107,7 +228,22 @@ pub enum Transformation {
         input_path: String,
         #[serde(rename = "outputPath")]
         output_path: String,
+        #[serde(default)]
+        dialect: PreprocessDialect,
+        #[serde(default)]
+        escape: EscapeMode,
+        #[serde(default)]
+        encoding: OutputEncoding,
+        #[serde(rename = "lineEndings", default)]
+        line_endings: LineEndings,
+        #[serde(rename = "forEachLocale", default)]
+        for_each_locale: bool,
+        /// Locale a `forEachLocale` entry was expanded for, filled in by
+        /// `transformations::expand`
+        #[serde(skip)]
+        locale: Option<String>,
     },
+    /// Copies an image and sets its DPI with `sips` (macOS only)
     CopyImageMac {
         #[serde(rename = "fileType")]
         file_type: FileType,
@@ -118,6 +254,7 @@ pub enum Transformation {
         #[serde(rename = "dpi")]
         dpi: Option<f64>,
     },
+    /// Generates macOS .DS_Store files for disk images
     DsStore {
         #[serde(rename = "outputPath")]
         output_path: String,
@@ -144,33 +281,285 @@ pub enum Transformation {
     },
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+/// One file rendered by a `raster` entry with `outputs`.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct RasterOutput {
+    #[serde(rename = "outputPath")]
+    pub output_path: String,
+    pub width: u32,
+    pub height: u32,
+    #[serde(rename = "paddingPixelsWidth")]
+    pub padding_pixels_width: Option<u32>,
+    #[serde(rename = "paddingPixelsHeight")]
+    pub padding_pixels_height: Option<u32>,
+    #[serde(rename = "offsetX")]
+    pub offset_x: Option<i32>,
+    #[serde(rename = "offsetY")]
+    pub offset_y: Option<i32>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 pub struct TransformationEntry {
+    /// Brands (by `env.name`) this entry runs for; all brands when omitted
     #[serde(default)]
     pub only: Option<Vec<String>>,
+    /// Condition on the brand's `env` and `strings`, written as in `{{#if}}`
+    /// (e.g. `name != official && channel == beta`); always runs when omitted
+    #[serde(default)]
+    pub when: Option<String>,
     #[serde(flatten)]
     pub transformation: Transformation,
+    /// Where the entry was defined, filled in by the loader
+    #[serde(skip)]
+    pub origin: Option<EntryOrigin>,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+/// The config file and position a transformation entry was read from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryOrigin {
+    /// File path relative to the repo root, e.g. `config/windows.json`
+    pub file: String,
+    /// Index of the entry in that file's `transformations`
+    pub index: usize,
+}
+
+impl std::fmt::Display for EntryOrigin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} transformations[{}]", self.file, self.index)
+    }
+}
+
+impl TransformationEntry {
+    /// Whether the entry runs for the brand with this `env.name`.
+    pub fn applies_to(&self, brand_name: Option<&str>) -> bool {
+        match &self.only {
+            Some(only_brands) => {
+                brand_name.is_some_and(|name| only_brands.iter().any(|b| b == name))
+            }
+            None => true,
+        }
+    }
+
+    /// Whether the entry's `when` condition holds for this brand.
+    pub fn matches_when(&self, brand_config: &BrandConfig) -> bool {
+        self.when
+            .as_deref()
+            .is_none_or(|condition| evaluate_condition_expression(condition, brand_config))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 pub struct Config {
+    /// Path of each brand's config file, relative to the brand folder
     #[serde(rename = "brandConfigPath")]
     pub brand_config_path: String,
+    /// Extra config files, relative to the repo root, whose transformations
+    /// are appended in order after this file's own
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Fields for transformations that don't set them, keyed by `type`; also
+    /// applied to included files and brand overlays
+    #[serde(default)]
+    pub defaults: TransformationDefaults,
     pub transformations: Vec<TransformationEntry>,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize, Default)]
-pub struct BrandConfig {
+/// Default field values per transformation type, e.g.
+/// `{ "raster": { "fit": "contain" } }`.
+pub type TransformationDefaults = BTreeMap<String, serde_json::Map<String, Value>>;
+
+/// A config file listed in another config file's `include`.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct ConfigInclude {
+    /// Further config files, relative to the repo root
     #[serde(default)]
-    pub strings: HashMap<String, String>,
+    pub include: Vec<String>,
     #[serde(default)]
+    pub transformations: Vec<TransformationEntry>,
+}
+
+/// File name of a brand's overlay on `config.json`, in its brand folder.
+pub const CONFIG_OVERLAY_FILE: &str = "config.overlay.json";
+
+/// Changes a brand makes to the shared transformations for itself only.
+/// Removals and patches apply to the shared entries; `transformations` are
+/// appended afterwards.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+pub struct ConfigOverlay {
+    /// Transformations appended after the shared ones
+    #[serde(default)]
+    pub transformations: Vec<TransformationEntry>,
+    /// Output paths of shared transformations to drop
+    #[serde(default)]
+    pub remove: Vec<String>,
+    /// Field changes for shared transformations
+    #[serde(default)]
+    pub patch: Vec<TransformationPatch>,
+}
+
+/// Fields to change on every shared transformation with a given `outputPath`.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct TransformationPatch {
+    #[serde(rename = "outputPath")]
+    pub output_path: String,
+    /// Fields to set, named as in config.json
+    pub set: serde_json::Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema, Default)]
+#[serde(from = "RawBrandConfig")]
+pub struct BrandConfig {
+    /// Sibling brand to inherit strings, env and source files from
+    pub extends: Option<String>,
+    pub strings: HashMap<String, String>,
     pub env: HashMap<String, String>,
+    /// Structured values (lists and objects) for `{{#each}}` loops, and the
+    /// typed form of any `strings` entry that wasn't given as a string
+    pub data: HashMap<String, Value>,
+    /// Per-locale overrides of `strings`, keyed by locale code
+    pub locales: BTreeMap<String, HashMap<String, String>>,
+}
+
+/// Contents of a brand's `brand-config.json`. `strings` may hold numbers,
+/// booleans, lists and objects as well as text.
+#[derive(Deserialize, JsonSchema)]
+struct RawBrandConfig {
+    /// Sibling brand to inherit strings, env and source files from
+    #[serde(default)]
+    extends: Option<String>,
+    /// Values for `{{#str}}`; numbers, booleans and lists are kept as typed values
+    #[serde(default)]
+    strings: HashMap<String, Value>,
+    /// Values for `{{#if}}` conditions; `name` selects `only` entries
+    #[serde(default)]
+    env: HashMap<String, String>,
+    /// Lists and objects for `{{#each}}` loops
+    #[serde(default)]
+    data: HashMap<String, Value>,
+    /// Per-locale overrides of `strings`, keyed by locale code
+    #[serde(default)]
+    locales: BTreeMap<String, HashMap<String, String>>,
+}
+
+impl From<RawBrandConfig> for BrandConfig {
+    fn from(raw: RawBrandConfig) -> Self {
+        let mut config = BrandConfig {
+            extends: raw.extends,
+            strings: HashMap::new(),
+            env: raw.env,
+            data: raw.data,
+            locales: raw.locales,
+        };
+
+        // Typed values keep their JSON form in `data` and get a text form in
+        // `strings` so `{{#str}}` can still print them
+        for (key, value) in raw.strings {
+            if let Value::String(text) = value {
+                config.strings.insert(key, text);
+                continue;
+            }
+            if let Some(text) = value_text(&value) {
+                config.strings.insert(key.clone(), text);
+            }
+            config.data.insert(key, value);
+        }
+
+        config
+    }
+}
+
+/// Text form of a typed value: scalars as written and lists of scalars
+/// joined with spaces. Objects and nested lists have none.
+pub fn value_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.clone()),
+        Value::Null => Some(String::new()),
+        Value::Bool(_) | Value::Number(_) => Some(value.to_string()),
+        Value::Array(items) => items
+            .iter()
+            .map(|item| match item {
+                Value::Array(_) | Value::Object(_) => None,
+                _ => value_text(item),
+            })
+            .collect::<Option<Vec<_>>>()
+            .map(|parts| parts.join(" ")),
+        Value::Object(_) => None,
+    }
+}
+
+impl BrandConfig {
+    /// Copy of this config with a loop variable bound to `value`.
+    ///
+    /// The value is stored in `data` under `name`, and every scalar inside it
+    /// is exposed in both `strings` and `env` under its dotted path (`item`,
+    /// `item.size`, `item.tile.width`), so `{{#str}}` and `{{#if}}` can read it.
+    /// Nested lists and objects are also stored in `data` for inner loops.
+    pub fn with_loop_variable(&self, name: &str, value: &Value) -> BrandConfig {
+        let mut scoped = self.clone();
+        bind_value(&mut scoped, name, value);
+        scoped
+    }
+
+    /// Locales to generate for `forEachLocale` entries: `en-US` first, then
+    /// every locale declared under `locales`.
+    pub fn locale_codes(&self) -> Vec<&str> {
+        let mut codes = vec![DEFAULT_LOCALE];
+        codes.extend(
+            self.locales
+                .keys()
+                .map(String::as_str)
+                .filter(|code| *code != DEFAULT_LOCALE),
+        );
+        codes
+    }
+
+    /// Copy of this config with the strings for `locale` applied.
+    ///
+    /// Keys the locale doesn't override keep their en-US value.
+    pub fn for_locale(&self, locale: &str) -> BrandConfig {
+        let mut localized = self.clone();
+        if let Some(overrides) = self.locales.get(locale) {
+            localized
+                .strings
+                .extend(overrides.iter().map(|(k, v)| (k.clone(), v.clone())));
+        }
+        localized
+    }
+}
+
+/// Locale the top-level `strings` are written in.
+pub const DEFAULT_LOCALE: &str = "en-US";
+
+fn bind_value(config: &mut BrandConfig, path: &str, value: &Value) {
+    match value {
+        Value::Object(fields) => {
+            config.data.insert(path.to_string(), value.clone());
+            for (key, field) in fields {
+                bind_value(config, &format!("{}.{}", path, key), field);
+            }
+        }
+        Value::Array(_) => {
+            config.data.insert(path.to_string(), value.clone());
+        }
+        Value::String(text) => {
+            config.strings.insert(path.to_string(), text.clone());
+            config.env.insert(path.to_string(), text.clone());
+        }
+        Value::Null => {
+            config.strings.insert(path.to_string(), String::new());
+            config.env.insert(path.to_string(), String::new());
+        }
+        Value::Bool(_) | Value::Number(_) => {
+            config.strings.insert(path.to_string(), value.to_string());
+            config.env.insert(path.to_string(), value.to_string());
+        }
+    }
 }
 
 impl Transformation {
     pub fn output_path(&self) -> &str {
         match self {
-            Transformation::Raster { output_path, .. } => output_path,
+            Transformation::Raster { output_path, .. } => output_path.as_deref().unwrap_or(""),
             Transformation::Ico { output_path, .. } => output_path,
             Transformation::Icns { output_path, .. } => output_path,
             Transformation::AssetsCar { output_path, .. } => output_path,
@@ -181,6 +570,129 @@ impl Transformation {
         }
     }
 
+    /// Check that a raster gives either `outputPath`, `width` and `height`,
+    /// or `outputs`, but not both.
+    pub fn check_outputs(&self) -> crate::error::Result<()> {
+        let Transformation::Raster {
+            input_path,
+            output_path,
+            width,
+            height,
+            outputs,
+            ..
+        } = self
+        else {
+            return Ok(());
+        };
+
+        let single = [output_path.is_some(), width.is_some(), height.is_some()];
+        let message = match outputs {
+            None if single.contains(&false) => "needs outputPath, width and height, or outputs",
+            Some(_) if single.contains(&true) => {
+                "outputPath, width and height go on each of outputs, not on the entry"
+            }
+            Some(outputs) if outputs.is_empty() => "has an empty outputs list",
+            _ => return Ok(()),
+        };
+        Err(crate::error::Error::Config(format!(
+            "raster of '{}': {}",
+            input_path, message
+        )))
+    }
+
+    /// Every path the entry writes: the `outputPath` of each of a raster's
+    /// `outputs`, otherwise its own `outputPath`, in every one of its `scales`.
+    pub fn output_paths(&self) -> Vec<String> {
+        let base_paths = match self {
+            Transformation::Raster {
+                outputs: Some(outputs),
+                ..
+            } => outputs
+                .iter()
+                .map(|output| output.output_path.as_str())
+                .collect(),
+            _ => vec![self.output_path()],
+        };
+        let scales = match self {
+            Transformation::Raster {
+                scales: Some(scales),
+                ..
+            } => scales.as_slice(),
+            _ => &[1],
+        };
+
+        base_paths
+            .into_iter()
+            .flat_map(|path| {
+                scales
+                    .iter()
+                    .map(move |&scale| scaled_output_path(path, scale))
+            })
+            .collect()
+    }
+
+    /// Every input and output path field, for placeholder expansion.
+    pub fn paths_mut(&mut self) -> Vec<&mut String> {
+        match self {
+            Transformation::Raster {
+                input_path,
+                output_path,
+                outputs,
+                ..
+            } => {
+                let mut paths = vec![input_path];
+                paths.extend(output_path.as_mut());
+                for output in outputs.iter_mut().flatten() {
+                    paths.push(&mut output.output_path);
+                }
+                paths
+            }
+            Transformation::Ico {
+                input_path,
+                output_path,
+                ..
+            }
+            | Transformation::Icns {
+                input_path,
+                output_path,
+                ..
+            }
+            | Transformation::Copy {
+                input_path,
+                output_path,
+                ..
+            }
+            | Transformation::CopyPreprocess {
+                input_path,
+                output_path,
+                ..
+            }
+            | Transformation::CopyImageMac {
+                input_path,
+                output_path,
+                ..
+            } => vec![input_path, output_path],
+            Transformation::AssetsCar {
+                liquid_glass_icon_path,
+                output_path,
+                app_icon_input,
+                icon_input,
+                ..
+            } => vec![
+                liquid_glass_icon_path,
+                output_path,
+                app_icon_input,
+                icon_input,
+            ],
+            Transformation::DsStore {
+                output_path,
+                background_image,
+                volume_icon,
+                ..
+            } => vec![output_path, background_image, volume_icon],
+        }
+    }
+
     pub fn transformation_type(&self) -> &str {
         match self {
             Transformation::Raster { .. } => "raster",
@@ -0,0 +1,139 @@
+use crate::error::{Error, Result};
+use crate::temp::TempDir;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// A brand that only exists for one run: an existing brand's sources, with
+/// some files replaced from paths given on the command line.
+#[derive(Debug, Clone)]
+pub struct VirtualBrand {
+    pub name: String,
+    pub assets: Vec<VirtualAsset>,
+}
+
+/// A replacement source file, given as `<path in brand>=<file>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VirtualAsset {
+    pub path: String,
+    pub file: PathBuf,
+}
+
+impl VirtualAsset {
+    pub fn parse(spec: &str) -> Result<Self> {
+        let (path, file) = spec.split_once('=').ok_or_else(|| {
+            Error::Config(format!(
+                "Invalid --asset '{}': expected <path in brand>=<file>",
+                spec
+            ))
+        })?;
+
+        let is_relative = !path.is_empty()
+            && Path::new(path)
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)));
+        if !is_relative {
+            return Err(Error::Config(format!(
+                "Invalid --asset '{}': '{}' must be a relative path inside the brand",
+                spec, path
+            )));
+        }
+
+        Ok(Self {
+            path: path.to_string(),
+            file: PathBuf::from(file),
+        })
+    }
+}
+
+impl VirtualBrand {
+    pub fn new(name: &str, assets: Vec<VirtualAsset>) -> Result<Self> {
+        let mut components = Path::new(name).components();
+        if !matches!(
+            (components.next(), components.next()),
+            (Some(Component::Normal(_)), None)
+        ) {
+            return Err(Error::Config(format!(
+                "Invalid --virtual-brand name '{}': expected a single directory name",
+                name
+            )));
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            assets,
+        })
+    }
+
+    /// Lay the replacement assets out under a temporary `<name>/` directory,
+    /// to be searched before the base brand's sources. The directory lives as
+    /// long as the returned `TempDir`.
+    pub fn materialize(&self) -> Result<(TempDir, PathBuf)> {
+        let temp_dir = TempDir::new("firefox-brand-virtual")?;
+        let brand_dir = temp_dir.create_dir(&self.name)?;
+
+        for asset in &self.assets {
+            if !asset.file.is_file() {
+                return Err(Error::FileNotFound(asset.file.clone()));
+            }
+            let destination = brand_dir.join(&asset.path);
+            if let Some(parent) = destination.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::copy(&asset.file, destination)?;
+        }
+
+        Ok((temp_dir, brand_dir))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_asset() {
+        let asset = VirtualAsset::parse("mac/disk.png=/tmp/pr-disk.png").unwrap();
+        assert_eq!(asset.path, "mac/disk.png");
+        assert_eq!(asset.file, PathBuf::from("/tmp/pr-disk.png"));
+
+        for invalid in [
+            "logo.svg",
+            "=logo.svg",
+            "../official/logo.svg=x",
+            "/etc/passwd=x",
+        ] {
+            assert!(VirtualAsset::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_materialize() {
+        let inputs = TempDir::new("virtual-brand-inputs").unwrap();
+        fs::write(inputs.join("logo.svg"), "<svg/>").unwrap();
+
+        let asset = VirtualAsset::parse(&format!(
+            "content/logo.svg={}",
+            inputs.join("logo.svg").display()
+        ))
+        .unwrap();
+        let brand = VirtualBrand::new("pr-123", vec![asset]).unwrap();
+        let (_temp_dir, brand_dir) = brand.materialize().unwrap();
+        assert!(brand_dir.ends_with("pr-123"));
+        assert_eq!(
+            fs::read_to_string(brand_dir.join("content/logo.svg")).unwrap(),
+            "<svg/>"
+        );
+
+        let asset = VirtualAsset::parse("logo.svg=/nonexistent/logo.svg").unwrap();
+        let missing = VirtualBrand::new("pr-123", vec![asset]).unwrap();
+        assert!(matches!(missing.materialize(), Err(Error::FileNotFound(_))));
+
+        for invalid in ["", "..", "a/b"] {
+            assert!(
+                VirtualBrand::new(invalid, Vec::new()).is_err(),
+                "{:?}",
+                invalid
+            );
+        }
+    }
+}
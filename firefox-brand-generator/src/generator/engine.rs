@@ -1,13 +1,18 @@
-use crate::config::{BrandConfig, Config};
+use crate::config::loader::brand_name;
+use crate::config::{BrandConfig, Config, Transformation};
 use crate::error::Result;
 use crate::generator::filter::{FilterOptions, filter_transformations};
 use crate::platform::PlatformCapabilities;
 use crate::transformations::{self, TransformationContext};
+use crate::utils::builtins::Builtins;
 use owo_colors::OwoColorize;
-use std::path::Path;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 pub struct GeneratorPaths<'a> {
-    pub source_dir: &'a Path,
+    /// Brand source directory followed by those of the brands it extends
+    pub source_dirs: &'a [PathBuf],
     pub static_dir: &'a Path,
     pub output_dir: &'a Path,
 }
@@ -56,83 +61,135 @@ pub fn generate(
     };
 
     // Filter transformations
-    let filtered = filter_transformations(&config.transformations, &effective_filter, &capabilities);
+    let (filtered, when_excluded) = filter_transformations(
+        &config.transformations,
+        &effective_filter,
+        &capabilities,
+        brand_config,
+    );
+
+    // Values for `{{#builtin}}` are fixed once so every file sees the same build time
+    let brand = brand_name(&paths.source_dirs[0]);
+    let builtins = Builtins::from_environment(&brand)?;
 
     // Create transformation context
     let ctx = TransformationContext {
-        source_dir: paths.source_dir,
+        source_dirs: paths.source_dirs,
         static_dir: paths.static_dir,
         output_dir: paths.output_dir,
         brand_config,
         capabilities: &capabilities,
+        builtins: &builtins,
+        static_overrides: RefCell::new(Vec::new()),
+        raster_sources: RefCell::new(HashMap::new()),
     };
 
     // Execute (or validate) each transformation
     let mut success_count = 0;
     let mut skip_count = 0;
     let mut error_count = 0;
+    let mut override_count = 0;
 
-    let action_verb = if validate_only { "Validating" } else { "Processing" };
-
-    for (transformation, should_warn) in filtered {
-        let t_type = transformation.transformation_type();
-        let output = transformation.output_path();
-
-        // Only honour missing-tool warnings when actually executing.
-        if should_warn && !validate_only {
-            eprintln!(
-                "{} {} transformation for '{}': {}",
-                "Skipping".yellow(),
-                t_type.cyan().bold(),
-                output.yellow(),
-                "required tool not available".dimmed()
-            );
-            skip_count += 1;
-            continue;
-        }
+    let action_verb = if validate_only {
+        "Validating"
+    } else {
+        "Processing"
+    };
 
-        // Check if we should skip based on filter
-        if let Some(ref only_types) = filter_options.only_types {
-            if !only_types.contains(t_type) {
+    for (entry, should_warn) in filtered {
+        // Paths may name brand values, e.g. `icon-{{#env channel}}.png`, and a
+        // raster with `outputs` or `scales` runs once per output file
+        let expanded: Vec<Result<Transformation>> =
+            match transformations::expand(&entry.transformation, brand_config) {
+                Ok(expanded) => expanded.into_iter().map(Ok).collect(),
+                Err(e) => vec![Err(e)],
+            };
+
+        for expanded in expanded {
+            let transformation = expanded.as_ref().unwrap_or(&entry.transformation);
+            let t_type = transformation.transformation_type();
+            let output = transformation.output_path();
+
+            // Only honour missing-tool warnings when actually executing.
+            if should_warn && !validate_only {
+                eprintln!(
+                    "{} {} transformation for '{}': {}",
+                    "Skipping".yellow(),
+                    t_type.cyan().bold(),
+                    output.yellow(),
+                    "required tool not available".dimmed()
+                );
                 skip_count += 1;
                 continue;
             }
-        }
 
-        print!(
-            "{} {} {} {}... ",
-            action_verb.dimmed(),
-            t_type.bold(),
-            "->".dimmed(),
-            output
-        );
+            // Check if we should skip based on filter
+            if let Some(ref only_types) = filter_options.only_types
+                && !only_types.contains(t_type)
+            {
+                skip_count += 1;
+                continue;
+            }
 
-        let result = if validate_only {
-            transformations::validate(&transformation, &ctx)
-        } else {
-            transformations::execute(&transformation, &ctx)
-        };
+            print!(
+                "{} {} {} {}... ",
+                action_verb.dimmed(),
+                t_type.bold(),
+                "->".dimmed(),
+                output
+            );
 
-        match result {
-            Ok(_) => {
-                println!("{}", "✓".green().bold());
-                success_count += 1;
+            let result = match expanded {
+                Ok(ref transformation) if validate_only => {
+                    transformations::validate(transformation, &ctx)
+                }
+                Ok(ref transformation) => transformations::execute(transformation, &ctx),
+                Err(e) => Err(e),
+            };
+
+            match result {
+                Ok(_) => {
+                    println!("{}", "✓".green().bold());
+                    success_count += 1;
+                }
+                Err(e) => {
+                    println!("{}", "✗".red().bold());
+                    eprintln!("  {}: {}", "Error".red().bold(), e);
+                    if let Some(origin) = &entry.origin {
+                        eprintln!("  {} {}", "Defined in".dimmed(), origin);
+                    }
+                    error_count += 1;
+                }
             }
-            Err(e) => {
-                println!("{}", "✗".red().bold());
-                eprintln!("  {}: {}", "Error".red().bold(), e.to_string());
-                error_count += 1;
+
+            for path in ctx.static_overrides.borrow().iter().skip(override_count) {
+                println!(
+                    "  {} {}",
+                    "Using static override".cyan(),
+                    path.display().to_string().dimmed()
+                );
             }
+            override_count = ctx.static_overrides.borrow().len();
         }
     }
 
-    let success_label = if validate_only { "Valid:   " } else { "Success: " };
+    let success_label = if validate_only {
+        "Valid:   "
+    } else {
+        "Success: "
+    };
 
     println!();
     println!("{}", "Summary:".bold().underline());
-    println!("  {}{}", success_label, success_count.to_string());
-    println!("  Skipped: {}", skip_count.to_string());
-    println!("  Errors:  {}", error_count.to_string());
+    println!("  {}{}", success_label, success_count);
+    println!("  Skipped: {}", skip_count);
+    println!("  Errors:  {}", error_count);
+    if when_excluded > 0 {
+        println!("  Excluded by when: {}", when_excluded);
+    }
+    if override_count > 0 {
+        println!("  Static overrides: {}", override_count);
+    }
 
     if error_count > 0 {
         let msg = if validate_only {
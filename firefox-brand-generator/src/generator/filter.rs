@@ -1,18 +1,13 @@
-use crate::config::{Transformation, TransformationEntry};
+use crate::config::{BrandConfig, TransformationEntry};
 use crate::platform::PlatformCapabilities;
 use std::collections::HashSet;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default)]
 pub enum MacMode {
     None,   // Skip ds-store, icns, assets-car, and copy-image-mac
     Simple, // Run icns, assets-car, and copy-image-mac only (skip ds-store)
-    All,    // Run all transformations (default behavior)
-}
-
-impl Default for MacMode {
-    fn default() -> Self {
-        MacMode::All
-    }
+    #[default]
+    All, // Run all transformations (default behavior)
 }
 
 #[derive(Clone)]
@@ -22,6 +17,12 @@ pub struct FilterOptions {
     pub brand_name: Option<String>,
 }
 
+impl Default for FilterOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl FilterOptions {
     pub fn new() -> Self {
         Self {
@@ -47,23 +48,27 @@ impl FilterOptions {
     }
 }
 
-pub fn filter_transformations(
-    transformations: &[TransformationEntry],
+/// Entries to run, each with whether its platform tool is missing, and the
+/// number of entries whose `when` condition excluded them for this brand.
+pub fn filter_transformations<'a>(
+    transformations: &'a [TransformationEntry],
     options: &FilterOptions,
     capabilities: &PlatformCapabilities,
-) -> Vec<(Transformation, bool)> {
-    transformations
+    brand_config: &BrandConfig,
+) -> (Vec<(&'a TransformationEntry, bool)>, usize) {
+    let mut when_excluded = 0;
+    let filtered = transformations
         .iter()
         .filter_map(|entry| {
             // Skip if the entry's `only` list is set and this brand isn't in it
-            if let Some(ref only_brands) = entry.only {
-                let brand_matches = options
-                    .brand_name
-                    .as_deref()
-                    .map_or(false, |name| only_brands.iter().any(|b| b == name));
-                if !brand_matches {
-                    return None;
-                }
+            if !entry.applies_to(options.brand_name.as_deref()) {
+                return None;
+            }
+
+            // Skip if the entry's `when` condition doesn't hold for this brand
+            if !entry.matches_when(brand_config) {
+                when_excluded += 1;
+                return None;
             }
 
             let t = &entry.transformation;
@@ -71,7 +76,10 @@ pub fn filter_transformations(
 
             // Check Mac mode filtering
             let mac_allowed = match options.mac_mode {
-                MacMode::None => !matches!(transformation_type, "ds-store" | "icns" | "assets-car" | "copy-image-mac"),
+                MacMode::None => !matches!(
+                    transformation_type,
+                    "ds-store" | "icns" | "assets-car" | "copy-image-mac"
+                ),
                 MacMode::Simple => !matches!(transformation_type, "ds-store"),
                 MacMode::All => true,
             };
@@ -100,7 +108,45 @@ pub fn filter_transformations(
 
             let should_warn = !platform_available;
 
-            Some((t.clone(), should_warn))
+            Some((entry, should_warn))
         })
-        .collect()
+        .collect();
+
+    (filtered, when_excluded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_when_conditions() {
+        let entries: Vec<TransformationEntry> = serde_json::from_str(
+            r#"[
+                { "type": "copy", "fileType": "source", "inputPath": "a", "outputPath": "always" },
+                { "type": "copy", "fileType": "source", "inputPath": "a", "outputPath": "beta", "when": "name != official && channel == beta" },
+                { "type": "copy", "fileType": "source", "inputPath": "a", "outputPath": "release", "when": "channel == release" }
+            ]"#,
+        )
+        .unwrap();
+        let brand_config = BrandConfig {
+            env: HashMap::from([
+                ("name".to_string(), "beta".to_string()),
+                ("channel".to_string(), "beta".to_string()),
+            ]),
+            ..Default::default()
+        };
+        let capabilities = PlatformCapabilities::default();
+
+        let options = FilterOptions::new().with_brand_name("beta".to_string());
+        let (filtered, when_excluded) =
+            filter_transformations(&entries, &options, &capabilities, &brand_config);
+        let outputs: Vec<&str> = filtered
+            .iter()
+            .map(|(entry, _)| entry.transformation.output_path())
+            .collect();
+        assert_eq!(outputs, ["always", "beta"]);
+        assert_eq!(when_excluded, 1);
+    }
 }
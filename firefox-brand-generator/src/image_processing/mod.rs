@@ -4,6 +4,6 @@ pub mod resizer;
 pub mod saver;
 
 pub use loader::{ImageSource, load};
-pub use rasterizer::{rasterize_svg, rasterize_svg_contain};
+pub use rasterizer::{parse_svg, rasterize_svg, rasterize_svg_contain, render_svg};
 pub use resizer::{resize, resize_with_padding};
 pub use saver::{save, save_png};
@@ -10,9 +10,23 @@ pub fn rasterize_svg(
     target_height: u32,
     fit: &FitStrategy,
 ) -> Result<DynamicImage> {
+    let tree = parse_svg(svg_data)?;
+    render_svg(&tree, target_width, target_height, fit)
+}
+
+/// Parse SVG data once so it can be rendered at several sizes
+pub fn parse_svg(svg_data: &[u8]) -> Result<usvg::Tree> {
     let opts = usvg::Options::default();
-    let tree = usvg::Tree::from_data(svg_data, &opts).map_err(|e| Error::Resvg(e.to_string()))?;
+    usvg::Tree::from_data(svg_data, &opts).map_err(|e| Error::Resvg(e.to_string()))
+}
 
+/// Render a parsed SVG to a specific size using a FitStrategy
+pub fn render_svg(
+    tree: &usvg::Tree,
+    target_width: u32,
+    target_height: u32,
+    fit: &FitStrategy,
+) -> Result<DynamicImage> {
     let svg_size = tree.size();
     let svg_width = svg_size.width();
     let svg_height = svg_size.height();
@@ -67,14 +81,10 @@ pub fn rasterize_svg(
     let mut pixmap = tiny_skia::Pixmap::new(render_width, render_height)
         .ok_or_else(|| Error::Resvg("Failed to create pixmap".to_string()))?;
 
-    // For non-uniform scaling (Fill), we need to apply different transforms
-    let transform = if matches!(fit, FitStrategy::Fill) {
-        tiny_skia::Transform::from_scale(scale_x, scale_y)
-    } else {
-        tiny_skia::Transform::from_scale(scale_x, scale_y)
-    };
+    // Fill uses non-uniform scaling; the other strategies pass the same scale for both axes
+    let transform = tiny_skia::Transform::from_scale(scale_x, scale_y);
 
-    resvg::render(&tree, transform, &mut pixmap.as_mut());
+    resvg::render(tree, transform, &mut pixmap.as_mut());
 
     // Convert from premultiplied to straight alpha
     let mut data = pixmap.data().to_vec();
@@ -7,14 +7,19 @@ pub mod temp;
 pub mod transformations;
 pub mod utils;
 
-pub use config::{BrandConfig, Config, load_brand_config, load_config};
+pub use config::{
+    BrandConfig, BrandOverride, Config, SchemaFile, VirtualAsset, VirtualBrand, load_brand_chain,
+    load_brand_config, load_config,
+};
 pub use error::{Error, Result};
 pub use generator::{FilterOptions, GeneratorPaths, MacMode, generate};
 pub use platform::is_macos;
 
+use owo_colors::OwoColorize;
 use std::path::Path;
 
 /// Main entry point for the library
+#[allow(clippy::too_many_arguments)]
 pub fn run(
     config_path: &Path,
     source_dir: &Path,
@@ -22,23 +27,102 @@ pub fn run(
     output_dir: &Path,
     filter_options: FilterOptions,
     validate_only: bool,
+    verbose: bool,
+    overrides: &[BrandOverride],
+    virtual_brand: Option<&VirtualBrand>,
 ) -> Result<()> {
     // Load configuration files
-    let config = load_config(config_path)?;
+    let mut config = load_config(config_path)?;
 
-    // Use the brand_config_path from the config, relative to source_dir
-    let brand_config_path = source_dir.join(&config.brand_config_path);
-    let brand_config = load_brand_config(&brand_config_path)?;
+    // Use the brand_config_path from the config, relative to source_dir,
+    // merged with the brands it extends
+    let mut chain = load_brand_chain(source_dir, &config.brand_config_path)?;
+
+    // A virtual brand builds on `source_dir` as its base, with its replacement
+    // assets searched first. The temporary directory must outlive generation.
+    let _virtual_dir = match virtual_brand {
+        Some(virtual_brand) => {
+            let (temp_dir, brand_dir) = virtual_brand.materialize()?;
+            chain.source_dirs.insert(0, brand_dir);
+            Some(temp_dir)
+        }
+        None => None,
+    };
+
+    if verbose {
+        let names: Vec<String> = chain
+            .source_dirs
+            .iter()
+            .map(|dir| config::loader::brand_name(dir))
+            .collect();
+        println!("Brand resolution chain: {}", names.join(" -> "));
+    }
+
+    // Brands adjust the shared transformations before any filtering
+    let unmatched_overlays =
+        config::apply_brand_overlays(&mut config, config_path, &chain.source_dirs)?;
+    for unmatched in &unmatched_overlays {
+        eprintln!("{} {}", "Warning:".yellow().bold(), unmatched);
+    }
+
+    // Command-line and environment overrides win over every brand-config.json
+    for brand_override in overrides {
+        println!("Override {}", brand_override);
+        brand_override.apply(&mut chain.brand_config);
+    }
+
+    // A virtual brand is filtered like its base unless told otherwise
+    if let Some(virtual_brand) = virtual_brand
+        && let Some(name) = chain.brand_config.env.get("name")
+        && *name != virtual_brand.name
+    {
+        eprintln!(
+            "{} virtual brand '{}' keeps env.name '{}' from its base, so `only` lists and conditions treat it as '{}' (use --set env.name={} to change this)",
+            "Warning:".yellow().bold(),
+            virtual_brand.name,
+            name,
+            name,
+            virtual_brand.name
+        );
+    }
+
+    // Typed fields are checked up front, before any platform filtering
+    let brand_name = chain.brand_config.env.get("name").map(String::as_str);
+    for entry in &config.transformations {
+        if entry.applies_to(brand_name) && entry.matches_when(&chain.brand_config) {
+            transformations::check_typed_fields(&entry.transformation, &chain.brand_config)
+                .map_err(|e| match (e, &entry.origin) {
+                    (Error::Config(msg), Some(origin)) => {
+                        Error::Config(format!("{}: {}", origin, msg))
+                    }
+                    (e, _) => e,
+                })?;
+        }
+    }
 
     // Set up paths
     let paths = GeneratorPaths {
-        source_dir,
+        source_dirs: &chain.source_dirs,
         static_dir,
         output_dir,
     };
 
     // Run the generator
-    generate(&config, &brand_config, &paths, &filter_options, validate_only)?;
+    generate(
+        &config,
+        &chain.brand_config,
+        &paths,
+        &filter_options,
+        validate_only,
+    )?;
+
+    // An overlay entry that matches nothing is likely a stale outputPath
+    if validate_only && !unmatched_overlays.is_empty() {
+        return Err(Error::Config(format!(
+            "{} overlay change(s) matched no transformation",
+            unmatched_overlays.len()
+        )));
+    }
 
     Ok(())
 }
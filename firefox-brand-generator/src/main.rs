@@ -1,5 +1,7 @@
-use clap::{Parser, ValueEnum};
-use firefox_brand_generator::{FilterOptions, MacMode, is_macos, run};
+use clap::{Parser, Subcommand, ValueEnum};
+use firefox_brand_generator::{
+    BrandOverride, FilterOptions, MacMode, SchemaFile, VirtualAsset, VirtualBrand, is_macos, run,
+};
 use owo_colors::OwoColorize;
 use std::path::{Path, PathBuf};
 use std::process;
@@ -21,13 +23,46 @@ impl From<MacModeArg> for MacMode {
     }
 }
 
+#[derive(Debug, Clone, ValueEnum)]
+enum SchemaFileArg {
+    Config,
+    ConfigInclude,
+    ConfigOverlay,
+    BrandConfig,
+}
+
+impl From<SchemaFileArg> for SchemaFile {
+    fn from(file: SchemaFileArg) -> Self {
+        match file {
+            SchemaFileArg::Config => SchemaFile::Config,
+            SchemaFileArg::ConfigInclude => SchemaFile::ConfigInclude,
+            SchemaFileArg::ConfigOverlay => SchemaFile::ConfigOverlay,
+            SchemaFileArg::BrandConfig => SchemaFile::BrandConfig,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the JSON Schema for one of the config files
+    Schema {
+        /// Which file to print the schema for
+        #[arg(value_enum, default_value = "config")]
+        file: SchemaFileArg,
+    },
+}
+
 #[derive(Parser)]
 #[command(
     name = "firefox-brand-generator",
     about = "Generate Firefox brand assets from source files",
-    version
+    version,
+    args_conflicts_with_subcommands = true
 )]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Brand to build (e.g. official, nightly, aurora, unofficial).
     /// Omit to build every brand found under <ROOT>/brands/
     brand: Option<String>,
@@ -58,6 +93,28 @@ struct Cli {
     /// Skips platform tool checks and all filesystem writes — safe to run on Linux CI.
     #[arg(long, conflicts_with_all = ["only", "mac", "output"])]
     validate: bool,
+
+    /// Override a brand value for this run, as strings.<key>=<value> or env.<key>=<value>.
+    /// Repeatable; applied after FXBRAND_STRING_<key> environment variables
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    set: Vec<String>,
+
+    /// Build a brand that exists only for this run, written to <OUTPUT>/<NAME>/.
+    /// Requires --base; combine with --set and --asset to change it
+    #[arg(long, value_name = "NAME", requires = "base", conflicts_with = "brand")]
+    virtual_brand: Option<String>,
+
+    /// Existing brand whose sources and config a --virtual-brand starts from
+    #[arg(long, value_name = "BRAND", requires = "virtual_brand")]
+    base: Option<String>,
+
+    /// Replace a source file of the --virtual-brand, as <path in brand>=<file>. Repeatable
+    #[arg(long = "asset", value_name = "PATH=FILE", requires = "virtual_brand")]
+    assets: Vec<String>,
+
+    /// Print extra detail, such as the brand inheritance chain
+    #[arg(short, long)]
+    verbose: bool,
 }
 
 fn make_filter_options(only: Option<Vec<String>>, mac: Option<MacModeArg>) -> FilterOptions {
@@ -122,6 +179,11 @@ fn discover_brands(brands_dir: &Path) -> Vec<String> {
 fn main() {
     let cli = Cli::parse();
 
+    if let Some(Command::Schema { file }) = cli.command {
+        println!("{}", SchemaFile::from(file).to_json());
+        return;
+    }
+
     let root = if let Some(r) = cli.root {
         if !r.join("config.json").exists() || !r.join("brands").is_dir() {
             eprintln!(
@@ -152,7 +214,35 @@ fn main() {
     let brands_dir = root.join("brands");
     let available_brands = discover_brands(&brands_dir);
 
-    let brands_to_build: Vec<String> = if let Some(brand) = cli.brand {
+    let virtual_brand = cli.virtual_brand.map(|name| {
+        let assets: firefox_brand_generator::Result<Vec<_>> = cli
+            .assets
+            .iter()
+            .map(|spec| VirtualAsset::parse(spec))
+            .collect();
+        match assets.and_then(|assets| VirtualBrand::new(&name, assets)) {
+            Ok(virtual_brand) => virtual_brand,
+            Err(e) => {
+                eprintln!("{} {}", "Error:".red().bold(), e);
+                process::exit(1);
+            }
+        }
+    });
+
+    if let Some(ref virtual_brand) = virtual_brand
+        && available_brands.contains(&virtual_brand.name)
+    {
+        eprintln!(
+            "{} '{}' is already a brand under {}; pick another --virtual-brand name",
+            "Error:".red().bold(),
+            virtual_brand.name.yellow(),
+            brands_dir.display().to_string().yellow()
+        );
+        process::exit(1);
+    }
+
+    // A virtual brand is built from its --base brand's directory
+    let brands_to_build: Vec<String> = if let Some(brand) = cli.base.or(cli.brand) {
         if !available_brands.contains(&brand) {
             eprintln!(
                 "{} Brand '{}' not found under {}",
@@ -179,6 +269,20 @@ fn main() {
         available_brands.clone()
     };
 
+    // vars_os, because vars() panics on any variable that isn't valid Unicode
+    let env_vars = std::env::vars_os()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)));
+    let mut overrides = BrandOverride::from_env_vars(env_vars);
+    for spec in &cli.set {
+        match BrandOverride::parse(spec) {
+            Ok(brand_override) => overrides.push(brand_override),
+            Err(e) => {
+                eprintln!("{} {}", "Error:".red().bold(), e);
+                process::exit(1);
+            }
+        }
+    }
+
     let config_path = root.join("config.json");
     let static_dir = root.join("static");
     let output_parent = cli.output.unwrap_or_else(|| root.join("dist"));
@@ -194,18 +298,29 @@ fn main() {
 
     let mut errors: Vec<String> = Vec::new();
     let (action_heading, success_msg, failure_msg) = if validate_only {
-        ("Validating", "Brand validation passed!", "Validation failed for")
+        (
+            "Validating",
+            "Brand validation passed!",
+            "Validation failed for",
+        )
     } else {
-        ("Building", "Brand asset generation completed successfully!", "Generation failed for")
+        (
+            "Building",
+            "Brand asset generation completed successfully!",
+            "Generation failed for",
+        )
     };
 
     for brand in &brands_to_build {
         if multiple {
-            println!("\n{}", format!("=== {} {} ===", action_heading, brand).bold());
+            println!(
+                "\n{}",
+                format!("=== {} {} ===", action_heading, brand).bold()
+            );
         }
 
         let source = brands_dir.join(brand);
-        let output = output_parent.join(brand);
+        let output = output_parent.join(virtual_brand.as_ref().map_or(brand, |v| &v.name));
 
         match run(
             &config_path,
@@ -214,6 +329,9 @@ fn main() {
             &output,
             filter_options.clone(),
             validate_only,
+            cli.verbose,
+            &overrides,
+            virtual_brand.as_ref(),
         ) {
             Ok(_) => {
                 println!("\n{} {}", "✓".green().bold(), success_msg.green());
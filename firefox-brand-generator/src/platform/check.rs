@@ -35,17 +35,16 @@ impl PlatformCapabilities {
         }
 
         // Check macOS version (requires macOS 15.0+ / Darwin 25.0+)
-        if let Ok(darwin_version) = get_darwin_version() {
-            if let Some(major_version) = parse_darwin_major_version(&darwin_version) {
-                if major_version < 25 {
-                    return Err(Error::UnsupportedToolVersion {
-                        tool: "macOS".to_string(),
-                        version: format!("Darwin {}", darwin_version),
-                        message: "actool .icon support requires macOS 15 (Darwin 25.0) or higher"
-                            .to_string(),
-                    });
-                }
-            }
+        if let Ok(darwin_version) = get_darwin_version()
+            && let Some(major_version) = parse_darwin_major_version(&darwin_version)
+            && major_version < 25
+        {
+            return Err(Error::UnsupportedToolVersion {
+                tool: "macOS".to_string(),
+                version: format!("Darwin {}", darwin_version),
+                message: "actool .icon support requires macOS 15 (Darwin 25.0) or higher"
+                    .to_string(),
+            });
         }
 
         // Check actool version
@@ -119,16 +118,13 @@ fn get_actool_version() -> Result<String> {
     }
     let version_output = String::from_utf8_lossy(&output.stdout);
 
-    if let Some(version_section) = version_output.split("com.apple.actool.version").nth(1) {
-        if let Some(short_bundle_version) = version_section.split("short-bundle-version").nth(1) {
-            if let Some(version_start) = short_bundle_version.find("<string>") {
-                if let Some(version_end) = short_bundle_version[version_start..].find("</string>") {
-                    let version_value =
-                        &short_bundle_version[version_start + 8..version_start + version_end];
-                    return Ok(version_value.trim().to_string());
-                }
-            }
-        }
+    if let Some(version_section) = version_output.split("com.apple.actool.version").nth(1)
+        && let Some(short_bundle_version) = version_section.split("short-bundle-version").nth(1)
+        && let Some(version_start) = short_bundle_version.find("<string>")
+        && let Some(version_end) = short_bundle_version[version_start..].find("</string>")
+    {
+        let version_value = &short_bundle_version[version_start + 8..version_start + version_end];
+        return Ok(version_value.trim().to_string());
     }
 
     Err(Error::Config(
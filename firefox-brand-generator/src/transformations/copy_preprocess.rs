@@ -1,210 +1,456 @@
-use crate::config::types::BrandConfig;
+use crate::config::types::{
+    BrandConfig, DEFAULT_LOCALE, LineEndings, OutputEncoding, PreprocessDialect,
+};
 use crate::error::{Error, Result};
-use crate::utils::string_processing;
+use crate::utils::builtins::Builtins;
+use crate::utils::conditions::evaluate_condition_expression;
+use crate::utils::mozilla_preprocessor;
+use crate::utils::string_processing::{self, StringFilter};
+use crate::utils::syntax_check;
 use owo_colors::OwoColorize;
 use regex::Regex;
+use serde_json::Value;
 use std::fs;
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
+
+/// Settings shared by every file preprocessed for one output, including
+/// files pulled in by `{{#include}}`.
+pub struct PreprocessOptions<'a> {
+    pub brand_config: &'a BrandConfig,
+    /// Directories searched for `{{#include}}` targets, in order
+    pub include_dirs: &'a [&'a Path],
+    /// Escape applied to `{{#str}}` values that don't name one of their own
+    pub default_escape: Option<StringFilter>,
+    pub dialect: PreprocessDialect,
+    /// Values for `{{#builtin name}}`
+    pub builtins: &'a Builtins,
+}
+
+pub fn execute(
+    input_path: &Path,
+    output_path: &Path,
+    options: &PreprocessOptions,
+    encoding: &OutputEncoding,
+    line_endings: &LineEndings,
+) -> Result<()> {
+    // Read and preprocess the input file, expanding any includes
+    let content = fs::read_to_string(input_path)
+        .map_err(|_| Error::FileNotFound(input_path.to_path_buf()))?;
+    let processed_content = preprocess_source(input_path, &content, options, &mut Vec::new())?;
+
+    // Warn and skip if all content was conditional and evaluated to nothing
+    if processed_content.trim().is_empty() {
+        eprintln!(
+            "{} Skipping '{}': processed content is empty (all content was inside unmatched conditionals)",
+            "Warning:".yellow().bold(),
+            output_path.display()
+        );
+        return Ok(());
+    }
+
+    // Preprocessing normalises to LF, so restore CRLF if asked to or if the input used it
+    let use_crlf = match line_endings {
+        LineEndings::Lf => false,
+        LineEndings::Crlf => true,
+        LineEndings::Preserve => content.contains("\r\n"),
+    };
+    let output_content = if use_crlf {
+        processed_content.replace('\n', "\r\n")
+    } else {
+        processed_content.clone()
+    };
+
+    // Catch broken Fluent/properties output here rather than in the Firefox build
+    syntax_check::check_output_syntax(output_path, &processed_content)?;
+
+    // Ensure the output directory exists
+    if let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    // Write the processed content to the output file
+    fs::write(output_path, encode(&output_content, encoding))?;
+
+    Ok(())
+}
 
-// Helper function to evaluate a single condition
-fn evaluate_single_condition(
-    var_name: &str,
-    operator: &str,
-    expected_value: &str,
+/// The locale code and output path of each file a `forEachLocale` entry
+/// writes: the `en-US` component of `output_path` is replaced by the locale
+/// code, and the locale's string overrides must all be defined for `en-US`.
+pub fn locale_targets(
+    output_path: &str,
     brand_config: &BrandConfig,
-) -> bool {
-    brand_config.env.get(var_name).map_or(false, |value| {
-        match operator {
-            "==" => value == expected_value,
-            "!=" => value != expected_value,
-            _ => false, // Unsupported operator
+) -> Result<Vec<(String, String)>> {
+    if !output_path.split('/').any(|part| part == DEFAULT_LOCALE) {
+        return Err(Error::Config(format!(
+            "forEachLocale output path '{}' must contain an '{}' directory",
+            output_path, DEFAULT_LOCALE
+        )));
+    }
+
+    for (locale, overrides) in &brand_config.locales {
+        let mut unknown: Vec<&str> = overrides
+            .keys()
+            .filter(|key| !brand_config.strings.contains_key(*key))
+            .map(String::as_str)
+            .collect();
+        if !unknown.is_empty() {
+            unknown.sort_unstable();
+            return Err(Error::Config(format!(
+                "Locale '{}' overrides strings that are not defined for {}: {}",
+                locale,
+                DEFAULT_LOCALE,
+                unknown.join(", ")
+            )));
         }
-    })
+    }
+
+    Ok(brand_config
+        .locale_codes()
+        .into_iter()
+        .map(|locale| {
+            let localized_path: Vec<&str> = output_path
+                .split('/')
+                .map(|part| if part == DEFAULT_LOCALE { locale } else { part })
+                .collect();
+            (locale.to_string(), localized_path.join("/"))
+        })
+        .collect())
 }
 
-// Parse and evaluate a complex condition expression
-fn evaluate_condition_expression(expression: &str, brand_config: &BrandConfig) -> bool {
-    // Trim whitespace from the expression
-    let expression = expression.trim();
+fn encode(content: &str, encoding: &OutputEncoding) -> Vec<u8> {
+    // Any BOM carried over from the input is replaced by the one for the output encoding
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
 
-    // Early return for empty expressions
-    if expression.is_empty() {
-        return false;
+    match encoding {
+        OutputEncoding::Utf8 => content.as_bytes().to_vec(),
+        OutputEncoding::Utf8Bom => {
+            let mut bytes = vec![0xEF, 0xBB, 0xBF];
+            bytes.extend_from_slice(content.as_bytes());
+            bytes
+        }
+        OutputEncoding::Utf16Le => {
+            let mut bytes = vec![0xFF, 0xFE];
+            bytes.extend(content.encode_utf16().flat_map(u16::to_le_bytes));
+            bytes
+        }
     }
+}
 
-    // Check if the entire expression is wrapped in parentheses
-    if expression.starts_with('(') && expression.ends_with(')') {
-        let inner = &expression[1..expression.len() - 1].trim();
-        if !inner.is_empty() {
-            // Make sure the parentheses are balanced
-            let mut depth = 0;
-            let mut balanced = true;
-
-            for c in inner.chars() {
-                if c == '(' {
-                    depth += 1;
-                } else if c == ')' {
-                    depth -= 1;
-                    if depth < 0 {
-                        balanced = false;
-                        break;
-                    }
-                }
-            }
+/// Preprocess the input in memory without writing anything, so that every
+/// `{{#include}}` reached for this brand is resolved and checked for cycles,
+/// and the result is syntax-checked as it would be before writing.
+pub fn validate(input_path: &Path, output_path: &Path, options: &PreprocessOptions) -> Result<()> {
+    let processed_content = preprocess_file(input_path, options, &mut Vec::new())?;
+    syntax_check::check_output_syntax(output_path, &processed_content)
+}
 
-            if balanced && depth == 0 {
-                // If parentheses are balanced, evaluate the inner expression
-                return evaluate_condition_expression(inner, brand_config);
-            }
-        }
+fn preprocess_file(
+    path: &Path,
+    options: &PreprocessOptions,
+    include_stack: &mut Vec<PathBuf>,
+) -> Result<String> {
+    let content = fs::read_to_string(path).map_err(|_| Error::FileNotFound(path.to_path_buf()))?;
+    preprocess_source(path, &content, options, include_stack)
+}
+
+fn preprocess_source(
+    path: &Path,
+    content: &str,
+    options: &PreprocessOptions,
+    include_stack: &mut Vec<PathBuf>,
+) -> Result<String> {
+    if let PreprocessDialect::Mozilla = options.dialect {
+        return mozilla_preprocessor::preprocess(path, content, options.brand_config);
     }
 
-    // Handle OR (||) operator - lowest precedence
-    if expression.contains("||") {
-        // Split by || but respect parentheses
-        let mut parts = Vec::new();
-        let mut current_part = String::new();
-        let mut paren_depth = 0;
-        let mut i = 0;
-
-        while i < expression.len() {
-            let c = expression.chars().nth(i).unwrap();
-
-            if c == '(' {
-                paren_depth += 1;
-                current_part.push(c);
-            } else if c == ')' {
-                paren_depth -= 1;
-                current_part.push(c);
-            } else if paren_depth == 0
-                && i + 1 < expression.len()
-                && c == '|'
-                && expression.chars().nth(i + 1).unwrap() == '|'
-            {
-                // Found || outside of parentheses, split here
-                parts.push(current_part);
-                current_part = String::new();
-                i += 1; // Skip the second '|'
-            } else {
-                current_part.push(c);
-            }
+    // Track the canonical path so the same file reached via different
+    // relative paths is still recognised as a cycle
+    let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    if include_stack.contains(&canonical) {
+        let chain: Vec<String> = include_stack
+            .iter()
+            .chain(std::iter::once(&canonical))
+            .map(|p| p.display().to_string())
+            .collect();
+        return Err(Error::Transformation(format!(
+            "Include cycle detected: {}",
+            chain.join(" -> ")
+        )));
+    }
 
-            i += 1;
-        }
+    include_stack.push(canonical);
+    let result = preprocess_content(content, options, include_stack);
+    include_stack.pop();
 
-        if !current_part.is_empty() {
-            parts.push(current_part);
-        }
+    result
+}
 
-        if !parts.is_empty() {
-            return parts
-                .iter()
-                .any(|part| evaluate_condition_expression(part.trim(), brand_config));
-        }
+fn preprocess_content(
+    content: &str,
+    options: &PreprocessOptions,
+    include_stack: &mut Vec<PathBuf>,
+) -> Result<String> {
+    // Drop maintainer comments before anything else can see them
+    let mut result = strip_comments(content)?;
+
+    // Resolve conditionals first so nothing in an untaken branch is evaluated.
+    // Loop bodies are left alone here and resolved per item with the loop
+    // variable bound.
+    result = process_if_blocks(&result, options.brand_config)?;
+
+    // Expand loops and includes, and fill in values on the lines around them
+    result = process_blocks(&result, options, include_stack)?;
+
+    Ok(result)
+}
+
+/// Replace `{{#builtin name}}` with the matching read-only value.
+fn process_builtins(content: &str, builtins: &Builtins) -> Result<String> {
+    let builtin_regex = Regex::new(r"\{\{#builtin\s+([^\s\}]+)\s*\}\}").unwrap();
+
+    let mut result = String::with_capacity(content.len());
+    let mut last_end = 0;
+    for caps in builtin_regex.captures_iter(content) {
+        let whole = caps.get(0).unwrap();
+        let name = caps.get(1).map_or("", |m| m.as_str());
+        let value = builtins.get(name).ok_or_else(|| {
+            Error::Transformation(format!(
+                "Unknown builtin '{}' (available: {})",
+                name,
+                Builtins::names().join(", ")
+            ))
+        })?;
+
+        result.push_str(&content[last_end..whole.start()]);
+        result.push_str(&value);
+        last_end = whole.end();
     }
+    result.push_str(&content[last_end..]);
 
-    // Handle AND (&&) operator - higher precedence than OR
-    if expression.contains("&&") {
-        // Split by && but respect parentheses
-        let mut parts = Vec::new();
-        let mut current_part = String::new();
-        let mut paren_depth = 0;
-        let mut i = 0;
-
-        while i < expression.len() {
-            let c = expression.chars().nth(i).unwrap();
-
-            if c == '(' {
-                paren_depth += 1;
-                current_part.push(c);
-            } else if c == ')' {
-                paren_depth -= 1;
-                current_part.push(c);
-            } else if paren_depth == 0
-                && i + 1 < expression.len()
-                && c == '&'
-                && expression.chars().nth(i + 1).unwrap() == '&'
-            {
-                // Found && outside of parentheses, split here
-                parts.push(current_part);
-                current_part = String::new();
-                i += 1; // Skip the second '&'
-            } else {
-                current_part.push(c);
-            }
+    Ok(result)
+}
 
-            i += 1;
+/// Remove `{{!-- ... --}}` comments, which may span several lines. A comment
+/// that is alone on its line(s) takes the line with it instead of leaving a
+/// blank one behind.
+fn strip_comments(content: &str) -> Result<String> {
+    const OPEN: &str = "{{!--";
+    const CLOSE: &str = "--}}";
+
+    let mut result = String::with_capacity(content.len());
+    let mut rest = content;
+
+    while let Some(start) = rest.find(OPEN) {
+        let end = rest[start + OPEN.len()..]
+            .find(CLOSE)
+            .map(|offset| start + OPEN.len() + offset + CLOSE.len())
+            .ok_or_else(|| {
+                let line = content.len() - rest.len() + start;
+                let line = content[..line].matches('\n').count() + 1;
+                Error::Transformation(format!("Unterminated {{{{!-- comment on line {}", line))
+            })?;
+
+        // The comment's line may have started before an earlier comment that
+        // was already consumed, in which case part of it is in `result`
+        let (line_start, result_line_start) = match rest[..start].rfind('\n') {
+            Some(i) => (i + 1, result.len()),
+            None => (0, result.rfind('\n').map_or(0, |i| i + 1)),
+        };
+        let line_end = rest[end..].find('\n').map_or(rest.len(), |i| end + i);
+        let alone_on_line = result[result_line_start..].trim().is_empty()
+            && rest[line_start..start].trim().is_empty()
+            && rest[end..line_end].trim().is_empty();
+
+        if alone_on_line {
+            result.truncate(result_line_start);
+            result.push_str(&rest[..line_start]);
+            // Also consume the newline that ended the comment's last line
+            rest = rest.get(line_end + 1..).unwrap_or("");
+        } else {
+            result.push_str(&rest[..start]);
+            rest = &rest[end..];
         }
+    }
 
-        if !current_part.is_empty() {
-            parts.push(current_part);
-        }
+    result.push_str(rest);
+    Ok(result)
+}
 
-        if !parts.is_empty() {
-            return parts
-                .iter()
-                .all(|part| evaluate_condition_expression(part.trim(), brand_config));
+fn each_start_regex() -> Regex {
+    Regex::new(r"^\s*\{\{#each\s+(\S+)\s+as\s+(\S+?)\s*\}\}\s*$").unwrap()
+}
+
+/// Find the `{{#endeach}}` matching the `{{#each}}` on `lines[start]`,
+/// allowing nested loops in the body.
+fn find_each_end(lines: &[&str], start: usize) -> Option<usize> {
+    let each_start = each_start_regex();
+    let each_end = Regex::new(r"^\s*\{\{#endeach\}\}\s*$").unwrap();
+
+    let mut depth = 0;
+    for (j, line) in lines.iter().enumerate().skip(start + 1) {
+        if each_start.is_match(line) {
+            depth += 1;
+        } else if each_end.is_match(line) {
+            if depth == 0 {
+                return Some(j);
+            }
+            depth -= 1;
         }
     }
+    None
+}
 
-    // Handle basic condition (var == value or var != value)
-    let re = Regex::new(r"^\s*([^\s=!]+)\s*(==|!=)\s*([^\s]+)\s*$").unwrap();
-    if let Some(caps) = re.captures(expression) {
-        let var_name = caps.get(1).map_or("", |m| m.as_str());
-        let operator = caps.get(2).map_or("", |m| m.as_str());
-        let expected_value = caps.get(3).map_or("", |m| m.as_str());
+/// Expand `{{#each list as item}}` ... `{{#endeach}}` blocks and
+/// `{{#include path}}` lines, and fill in builtins and string replacements on
+/// the remaining lines. Loop bodies and included files are preprocessed on
+/// their own, and their output is inserted as-is so that values coming from
+/// the brand config are never interpreted as directives.
+fn process_blocks(
+    content: &str,
+    options: &PreprocessOptions,
+    include_stack: &mut Vec<PathBuf>,
+) -> Result<String> {
+    let each_start = each_start_regex();
+    let include_line = Regex::new(r"^\s*\{\{#include\s+(.*?)\s*\}\}\s*$").unwrap();
 
-        return evaluate_single_condition(var_name, operator, expected_value, brand_config);
-    }
+    let lines: Vec<&str> = content.lines().collect();
+    let mut result_lines = Vec::with_capacity(lines.len());
+    let mut pending: Vec<&str> = Vec::new();
+    let mut i = 0;
 
-    // Invalid or unsupported expression format
-    println!(
-        "{} Invalid condition expression: '{}'",
-        "Warning:".yellow().bold(),
-        expression.yellow()
-    );
-    false
-}
+    while i < lines.len() {
+        if let Some(caps) = each_start.captures(lines[i]) {
+            flush_lines(&mut pending, &mut result_lines, options)?;
+
+            let list_key = caps.get(1).map_or("", |m| m.as_str());
+            let item_name = caps.get(2).map_or("", |m| m.as_str());
+            let end = find_each_end(&lines, i).ok_or_else(|| {
+                Error::Transformation(format!(
+                    "Unterminated {{{{#each {} as {}}}}} block (missing {{{{#endeach}}}})",
+                    list_key, item_name
+                ))
+            })?;
+
+            let mut body = lines[i + 1..end].join("\n");
+            body.push('\n');
+            for expanded in expand_each(list_key, item_name, &body, options, include_stack)? {
+                result_lines.extend(expanded.lines().map(str::to_string));
+            }
 
-pub fn execute(input_path: &Path, output_path: &Path, brand_config: &BrandConfig) -> Result<()> {
-    // Read the input file
-    let content = fs::read_to_string(input_path)
-        .map_err(|_| Error::FileNotFound(input_path.to_path_buf()))?;
+            i = end + 1;
+        } else if let Some(caps) = include_line.captures(lines[i]) {
+            flush_lines(&mut pending, &mut result_lines, options)?;
 
-    // Preprocess the content
-    let processed_content = preprocess_content(&content, brand_config)?;
+            let include_path = caps.get(1).map_or("", |m| m.as_str());
+            let resolved = resolve_include(include_path, options)?;
+            let included = preprocess_file(&resolved, options, include_stack)?;
+            if !included.is_empty() {
+                result_lines.push(included.strip_suffix('\n').unwrap_or(&included).to_string());
+            }
 
-    // Warn and skip if all content was conditional and evaluated to nothing
-    if processed_content.trim().is_empty() {
-        eprintln!(
-            "{} Skipping '{}': processed content is empty (all content was inside unmatched conditionals)",
-            "Warning:".yellow().bold(),
-            output_path.display()
-        );
-        return Ok(());
+            i += 1;
+        } else {
+            pending.push(lines[i]);
+            i += 1;
+        }
     }
+    flush_lines(&mut pending, &mut result_lines, options)?;
 
-    // Ensure the output directory exists
-    if let Some(parent) = output_path.parent() {
-        fs::create_dir_all(parent)?;
+    let mut output = result_lines.join("\n");
+    if content.ends_with('\n') && !output.is_empty() {
+        output.push('\n');
     }
+    Ok(output)
+}
 
-    // Write the processed content to the output file
-    fs::write(output_path, processed_content)?;
+/// Fill in builtins and string replacements on the collected plain lines and
+/// move them to the output.
+fn flush_lines(
+    pending: &mut Vec<&str>,
+    result_lines: &mut Vec<String>,
+    options: &PreprocessOptions,
+) -> Result<()> {
+    if pending.is_empty() {
+        return Ok(());
+    }
 
+    // Builtins go first so that brand values, which may contain anything,
+    // are substituted last and never processed again
+    let text = pending.join("\n");
+    pending.clear();
+    let text = process_builtins(&text, options.builtins)?;
+    result_lines.push(string_processing::process_string_replacements_with_escape(
+        &text,
+        options.brand_config,
+        options.default_escape,
+    )?);
     Ok(())
 }
 
-fn preprocess_content(content: &str, brand_config: &BrandConfig) -> Result<String> {
-    let mut result = String::from(content);
-
-    // Process conditional blocks
-    result = process_if_blocks(&result, brand_config)?;
+/// Preprocess a loop body once per element of `data.list`, with `item` bound
+/// to that element.
+fn expand_each(
+    list_key: &str,
+    item_name: &str,
+    body: &str,
+    options: &PreprocessOptions,
+    include_stack: &mut Vec<PathBuf>,
+) -> Result<Vec<String>> {
+    let items = match options.brand_config.data.get(list_key) {
+        Some(Value::Array(items)) => items,
+        Some(_) => {
+            return Err(Error::Transformation(format!(
+                "{{{{#each {}}}}} needs a list, but '{}' is not one",
+                list_key, list_key
+            )));
+        }
+        None => return Err(Error::MissingBrandConfigValue(list_key.to_string())),
+    };
+
+    items
+        .iter()
+        .map(|item| {
+            let scoped_config = options.brand_config.with_loop_variable(item_name, item);
+            let scoped_options = PreprocessOptions {
+                brand_config: &scoped_config,
+                ..*options
+            };
+            preprocess_content(body, &scoped_options, include_stack)
+        })
+        .collect()
+}
 
-    // Process string replacements
-    result = string_processing::process_string_replacements(&result, brand_config)?;
+/// Find the first file at `include_path` under the include directories.
+fn resolve_include(include_path: &str, options: &PreprocessOptions) -> Result<PathBuf> {
+    // Includes stay inside the include directories
+    if !Path::new(include_path)
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(Error::Transformation(format!(
+            "Included path '{}' must be relative and must not contain '..'",
+            include_path
+        )));
+    }
 
-    Ok(result)
+    options
+        .include_dirs
+        .iter()
+        .map(|dir| dir.join(include_path))
+        .find(|candidate| candidate.is_file())
+        .ok_or_else(|| {
+            let searched: Vec<String> = options
+                .include_dirs
+                .iter()
+                .map(|d| d.display().to_string())
+                .collect();
+            Error::Transformation(format!(
+                "Included file '{}' not found in {}",
+                include_path,
+                searched.join(", ")
+            ))
+        })
 }
 
 fn process_if_blocks(content: &str, brand_config: &BrandConfig) -> Result<String> {
@@ -219,6 +465,7 @@ fn process_if_blocks(content: &str, brand_config: &BrandConfig) -> Result<String
     let mut i = 0;
 
     // Regex patterns for identifying conditional blocks
+    let each_start = each_start_regex();
     let block_if_start = Regex::new(r"^\s*\{\{#if\s+(.*?)\s*\}\}\s*$").unwrap();
     let block_elseif = Regex::new(r"^\s*\{\{#elseif\s+(.*?)\s*\}\}\s*$").unwrap();
     let block_else = Regex::new(r"^\s*\{\{#else\}\}\s*$").unwrap();
@@ -227,6 +474,17 @@ fn process_if_blocks(content: &str, brand_config: &BrandConfig) -> Result<String
     while i < lines.len() {
         let line = lines[i];
 
+        // Loop bodies are resolved per item later, so pass them through whole
+        if each_start.is_match(line)
+            && let Some(end) = find_each_end(&lines, i)
+        {
+            if !in_block_conditional || !skip_until_endif {
+                result_lines.extend(lines[i..=end].iter().map(|l| l.to_string()));
+            }
+            i = end + 1;
+            continue;
+        }
+
         // Check for block conditional start
         if block_if_start.is_match(line) {
             // Extract condition expression
@@ -307,6 +565,7 @@ fn process_if_blocks(content: &str, brand_config: &BrandConfig) -> Result<String
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::temp::TempDir;
     use std::collections::HashMap;
 
     #[test]
@@ -319,6 +578,7 @@ mod tests {
         let brand_config = BrandConfig {
             strings: HashMap::new(),
             env,
+            ..Default::default()
         };
 
         // Test block-style conditional (true case)
@@ -408,6 +668,7 @@ mod tests {
         let brand_config = BrandConfig {
             strings,
             env: HashMap::new(),
+            ..Default::default()
         };
 
         // Test basic replacement
@@ -452,7 +713,11 @@ mod tests {
         env.insert("PLATFORM".to_string(), "macos".to_string());
         env.insert("DEBUG".to_string(), "true".to_string());
 
-        let brand_config = BrandConfig { strings, env };
+        let brand_config = BrandConfig {
+            strings,
+            env,
+            ..Default::default()
+        };
 
         // Test with simple condition
         let input = "{{#if PLATFORM == macos}}\n{{#str brand_name}} for Mac\n{{#else}}\n{{#str brand_name}} for Windows\n{{#endif}}";
@@ -495,4 +760,343 @@ mod tests {
 
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn test_process_includes() {
+        let mut strings = HashMap::new();
+        strings.insert("brand_name".to_string(), "Firefox".to_string());
+
+        let mut env = HashMap::new();
+        env.insert("name".to_string(), "official".to_string());
+
+        let brand_config = BrandConfig {
+            strings,
+            env,
+            ..Default::default()
+        };
+
+        let source_dir = TempDir::new("copy-preprocess-source").unwrap();
+        let static_dir = TempDir::new("copy-preprocess-static").unwrap();
+        let include_dirs = [source_dir.path(), static_dir.path()];
+        let options = PreprocessOptions {
+            brand_config: &brand_config,
+            include_dirs: &include_dirs,
+            default_escape: None,
+            dialect: PreprocessDialect::Template,
+            builtins: &Builtins::new("official", 0),
+        };
+
+        fs::create_dir_all(static_dir.join("partials")).unwrap();
+        fs::write(
+            static_dir.join("partials/header.txt"),
+            "{{#if name == official}}\nHeader for {{#str brand_name}}\n{{#endif}}\n",
+        )
+        .unwrap();
+        fs::write(
+            static_dir.join("main.txt"),
+            "{{#include partials/header.txt}}\nbody\n",
+        )
+        .unwrap();
+
+        // Included files are preprocessed with the same brand config
+        let result =
+            preprocess_file(&static_dir.join("main.txt"), &options, &mut Vec::new()).unwrap();
+        assert_eq!(result, "Header for Firefox\nbody\n");
+
+        // Brand sources take precedence over static files
+        fs::create_dir_all(source_dir.join("partials")).unwrap();
+        fs::write(source_dir.join("partials/header.txt"), "Brand header\n").unwrap();
+        let result =
+            preprocess_file(&static_dir.join("main.txt"), &options, &mut Vec::new()).unwrap();
+        assert_eq!(result, "Brand header\nbody\n");
+
+        // Includes inside unmatched conditionals are never resolved
+        fs::write(
+            static_dir.join("guarded.txt"),
+            "{{#if name == nightly}}\n{{#include missing.txt}}\n{{#endif}}\nbody\n",
+        )
+        .unwrap();
+        let result =
+            preprocess_file(&static_dir.join("guarded.txt"), &options, &mut Vec::new()).unwrap();
+        assert_eq!(result, "body\n");
+
+        // Missing includes are reported
+        fs::write(static_dir.join("missing.txt"), "{{#include nowhere.txt}}\n").unwrap();
+        assert!(
+            validate(
+                &static_dir.join("missing.txt"),
+                Path::new("out.txt"),
+                &options
+            )
+            .is_err()
+        );
+
+        // Include cycles are rejected
+        fs::write(static_dir.join("a.txt"), "a\n{{#include b.txt}}\n").unwrap();
+        fs::write(static_dir.join("b.txt"), "b\n{{#include a.txt}}\n").unwrap();
+        let err = validate(&static_dir.join("a.txt"), Path::new("out.txt"), &options).unwrap_err();
+        assert!(err.to_string().contains("Include cycle detected"));
+
+        // Includes can't reach outside the include directories
+        for escape in [
+            "../secrets.txt",
+            "partials/../../secrets.txt",
+            "/etc/passwd",
+        ] {
+            fs::write(
+                static_dir.join("escape.txt"),
+                format!("{{{{#include {}}}}}\n", escape),
+            )
+            .unwrap();
+            let err = validate(
+                &static_dir.join("escape.txt"),
+                Path::new("out.txt"),
+                &options,
+            )
+            .unwrap_err();
+            assert!(err.to_string().contains("must be relative"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_process_each_blocks() {
+        let mut strings = HashMap::new();
+        strings.insert("brand_name".to_string(), "Firefox".to_string());
+        strings.insert("directive".to_string(), "{{#str brand_name}}".to_string());
+
+        let mut data = HashMap::new();
+        data.insert("locales".to_string(), serde_json::json!(["de", "fr"]));
+        data.insert(
+            "tiles".to_string(),
+            serde_json::json!([
+                { "size": 44, "wide": false, "scales": [1, 2] },
+                { "size": 150, "wide": true, "scales": [1] }
+            ]),
+        );
+
+        let brand_config = BrandConfig {
+            strings,
+            data,
+            ..Default::default()
+        };
+        let options = PreprocessOptions {
+            brand_config: &brand_config,
+            include_dirs: &[],
+            default_escape: None,
+            dialect: PreprocessDialect::Template,
+            builtins: &Builtins::new("official", 0),
+        };
+
+        // Scalar items
+        let input = "start\n{{#each locales as locale}}\n{{#str brand_name}} {{#str locale}}\n{{#endeach}}\nend\n";
+        let result = preprocess_content(input, &options, &mut Vec::new()).unwrap();
+        assert_eq!(result, "start\nFirefox de\nFirefox fr\nend\n");
+
+        // Object fields, conditionals on the current item and nested loops
+        let input = "{{#each tiles as tile}}\n{{#if tile.wide == true}}\nwide {{#str tile.size}}\n{{#else}}\nsquare {{#str tile.size}}\n{{#endif}}\n{{#each tile.scales as scale}}\n  @{{#str scale}}x\n{{#endeach}}\n{{#endeach}}\n";
+        let result = preprocess_content(input, &options, &mut Vec::new()).unwrap();
+        assert_eq!(result, "square 44\n  @1x\n  @2x\nwide 150\n  @1x\n");
+
+        // Missing lists and unterminated loops are reported
+        let input = "{{#each missing as item}}\n{{#endeach}}\n";
+        assert!(preprocess_content(input, &options, &mut Vec::new()).is_err());
+        let input = "{{#each locales as locale}}\n{{#str locale}}\n";
+        assert!(preprocess_content(input, &options, &mut Vec::new()).is_err());
+
+        // Loops in untaken branches are never expanded
+        let input = "{{#if brand_name == Nightly}}\n{{#each missing as item}}\n{{#str item}}\n{{#endeach}}\n{{#endif}}\nend\n";
+        let result = preprocess_content(input, &options, &mut Vec::new()).unwrap();
+        assert_eq!(result, "end\n");
+
+        // Loop output is not preprocessed a second time
+        let input = "{{#each locales as locale}}\n{{#str directive}}\n{{#endeach}}\n";
+        let result = preprocess_content(input, &options, &mut Vec::new()).unwrap();
+        assert_eq!(result, "{{#str brand_name}}\n{{#str brand_name}}\n");
+    }
+
+    #[test]
+    fn test_strip_comments() {
+        // Whole-line comments take their line with them
+        let input = "a\n{{!-- note --}}\nb\n";
+        assert_eq!(strip_comments(input).unwrap(), "a\nb\n");
+
+        // Multi-line comments
+        let input = "a\n  {{!-- first line\n  second line --}}\nb";
+        assert_eq!(strip_comments(input).unwrap(), "a\nb");
+
+        // Inline comments leave the rest of the line alone
+        let input = "a {{!-- note --}}b\nc {{!-- spans\nlines --}} d\n";
+        assert_eq!(strip_comments(input).unwrap(), "a b\nc  d\n");
+
+        // Several comments on one line
+        let input = "a {{!-- x --}} {{!-- y --}}\nb\n";
+        assert_eq!(strip_comments(input).unwrap(), "a  \nb\n");
+        let input = "a\n {{!-- x --}} {{!-- y --}}\nb\n";
+        assert_eq!(strip_comments(input).unwrap(), "a\nb\n");
+
+        // Comments at the very end of the file
+        let input = "a\n{{!-- note --}}";
+        assert_eq!(strip_comments(input).unwrap(), "a\n");
+
+        // Unterminated comments are reported with their line
+        let err = strip_comments("a\nb {{!-- oops\n").unwrap_err();
+        assert!(err.to_string().contains("line 2"));
+    }
+
+    #[test]
+    fn test_output_encoding_and_line_endings() {
+        let brand_config = BrandConfig::default();
+        let options = PreprocessOptions {
+            brand_config: &brand_config,
+            include_dirs: &[],
+            default_escape: None,
+            dialect: PreprocessDialect::Template,
+            builtins: &Builtins::new("official", 0),
+        };
+        let dir = TempDir::new("copy-preprocess-encoding").unwrap();
+        let lf_input = dir.join("lf.txt");
+        let crlf_input = dir.join("crlf.txt");
+        let output = dir.join("out.txt");
+        fs::write(&lf_input, "a\nb\n").unwrap();
+        fs::write(&crlf_input, "a\r\nb\r\n").unwrap();
+
+        // Input line endings are preserved by default
+        execute(
+            &crlf_input,
+            &output,
+            &options,
+            &OutputEncoding::Utf8,
+            &LineEndings::Preserve,
+        )
+        .unwrap();
+        assert_eq!(fs::read(&output).unwrap(), b"a\r\nb\r\n");
+        execute(
+            &lf_input,
+            &output,
+            &options,
+            &OutputEncoding::Utf8,
+            &LineEndings::Preserve,
+        )
+        .unwrap();
+        assert_eq!(fs::read(&output).unwrap(), b"a\nb\n");
+
+        // Explicit line endings win over the input's
+        execute(
+            &lf_input,
+            &output,
+            &options,
+            &OutputEncoding::Utf8,
+            &LineEndings::Crlf,
+        )
+        .unwrap();
+        assert_eq!(fs::read(&output).unwrap(), b"a\r\nb\r\n");
+        execute(
+            &crlf_input,
+            &output,
+            &options,
+            &OutputEncoding::Utf8,
+            &LineEndings::Lf,
+        )
+        .unwrap();
+        assert_eq!(fs::read(&output).unwrap(), b"a\nb\n");
+
+        execute(
+            &lf_input,
+            &output,
+            &options,
+            &OutputEncoding::Utf8Bom,
+            &LineEndings::Lf,
+        )
+        .unwrap();
+        assert_eq!(fs::read(&output).unwrap(), b"\xEF\xBB\xBFa\nb\n");
+
+        execute(
+            &lf_input,
+            &output,
+            &options,
+            &OutputEncoding::Utf16Le,
+            &LineEndings::Crlf,
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read(&output).unwrap(),
+            b"\xFF\xFEa\0\r\0\n\0b\0\r\0\n\0"
+        );
+    }
+
+    #[test]
+    fn test_process_builtins() {
+        let builtins = Builtins::new("nightly", 1_735_689_600);
+
+        let input =
+            "Copyright {{#builtin year}} ({{#builtin brand}}, {{#builtin sourceDateEpoch}})";
+        assert_eq!(
+            process_builtins(input, &builtins).unwrap(),
+            "Copyright 2025 (nightly, 1735689600)"
+        );
+
+        let err = process_builtins("{{#builtin nope}}", &builtins).unwrap_err();
+        assert!(err.to_string().contains("Unknown builtin 'nope'"));
+
+        // Brand values that look like builtins come out as written
+        let mut strings = HashMap::new();
+        strings.insert("notice".to_string(), "{{#builtin year}}".to_string());
+        strings.insert("typo".to_string(), "{{#builtin nope}}".to_string());
+        let brand_config = BrandConfig {
+            strings,
+            ..Default::default()
+        };
+        let options = PreprocessOptions {
+            brand_config: &brand_config,
+            include_dirs: &[],
+            default_escape: None,
+            dialect: PreprocessDialect::Template,
+            builtins: &builtins,
+        };
+        let input = "{{#builtin year}} {{#str notice}} {{#str typo}}\n";
+        let result = preprocess_content(input, &options, &mut Vec::new()).unwrap();
+        assert_eq!(result, "2025 {{#builtin year}} {{#builtin nope}}\n");
+    }
+
+    #[test]
+    fn test_locale_targets() {
+        let mut strings = HashMap::new();
+        strings.insert("brandShortName".to_string(), "Firefox".to_string());
+        strings.insert("vendorShortName".to_string(), "Mozilla".to_string());
+        let mut de = HashMap::new();
+        de.insert("brandShortName".to_string(), "Feuerfuchs".to_string());
+        let brand_config = BrandConfig {
+            strings,
+            locales: [("de".to_string(), de)].into_iter().collect(),
+            ..Default::default()
+        };
+
+        let targets = locale_targets("locales/en-US/brand.ftl", &brand_config).unwrap();
+        assert_eq!(
+            targets,
+            [
+                ("en-US".to_string(), "locales/en-US/brand.ftl".to_string()),
+                ("de".to_string(), "locales/de/brand.ftl".to_string())
+            ]
+        );
+        let de = brand_config.for_locale("de");
+        assert_eq!(de.strings["brandShortName"], "Feuerfuchs");
+        // Keys the locale doesn't override fall back to en-US
+        assert_eq!(de.strings["vendorShortName"], "Mozilla");
+
+        let err = locale_targets("locales/brand.ftl", &brand_config).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("must contain an 'en-US' directory")
+        );
+
+        let mut typo = brand_config.clone();
+        typo.locales
+            .get_mut("de")
+            .unwrap()
+            .insert("brandShrtName".to_string(), "Feuerfuchs".to_string());
+        let err = locale_targets("locales/en-US/brand.ftl", &typo).unwrap_err();
+        assert!(err.to_string().contains("Locale 'de'"));
+        assert!(err.to_string().contains("brandShrtName"));
+    }
 }
@@ -7,49 +7,23 @@ use std::fs;
 use std::path::Path;
 use std::process::Command;
 
+/// Window and icon placement for the DMG window, as `[x, y]` and `[width, height]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DmgLayout {
+    pub window_position: [u32; 2],
+    pub window_size: [u32; 2],
+    pub app_icon_position: [u32; 2],
+    pub app_drop_link_position: [u32; 2],
+}
+
 pub fn execute(
     output_path: &Path,
     app_name: &str,
     volume_name: &str,
     background_image_path: &Path,
     volume_icon_path: &Path,
-    window_position: &str,
-    window_size: &str,
-    app_icon_position: &str,
-    app_drop_link_position: &str,
+    layout: &DmgLayout,
 ) -> Result<()> {
-    // Split the space-separated values (string substitution is done in mod.rs)
-    let window_pos_parts: Vec<&str> = window_position.split_whitespace().collect();
-    let window_size_parts: Vec<&str> = window_size.split_whitespace().collect();
-    let app_icon_pos_parts: Vec<&str> = app_icon_position.split_whitespace().collect();
-    let app_drop_link_pos_parts: Vec<&str> = app_drop_link_position.split_whitespace().collect();
-
-    // Validate that we have the expected number of parts
-    if window_pos_parts.len() != 2 {
-        return Err(Error::Transformation(format!(
-            "Invalid window position format: '{}'. Expected 'x y'",
-            window_position
-        )));
-    }
-    if window_size_parts.len() != 2 {
-        return Err(Error::Transformation(format!(
-            "Invalid window size format: '{}'. Expected 'width height'",
-            window_size
-        )));
-    }
-    if app_icon_pos_parts.len() != 2 {
-        return Err(Error::Transformation(format!(
-            "Invalid app icon position format: '{}'. Expected 'x y'",
-            app_icon_position
-        )));
-    }
-    if app_drop_link_pos_parts.len() != 2 {
-        return Err(Error::Transformation(format!(
-            "Invalid app drop link position format: '{}'. Expected 'x y'",
-            app_drop_link_position
-        )));
-    }
-
     // Create temporary directory structure
     let temp_dir = TempDir::new("firefox-brand-dsstore")?;
     let src_dir = temp_dir.create_dir("src")?;
@@ -88,22 +62,18 @@ pub fn execute(
         .arg("--background")
         .arg("background.png")
         .arg("--window-pos")
-        .arg(window_pos_parts[0])
-        .arg(window_pos_parts[1])
+        .args(layout.window_position.map(|v| v.to_string()))
         .arg("--window-size")
-        .arg(window_size_parts[0])
-        .arg(window_size_parts[1])
+        .args(layout.window_size.map(|v| v.to_string()))
         .arg("--icon-size")
         .arg("128")
         .arg("--text-size")
         .arg("12")
         .arg("--icon")
         .arg(app_name)
-        .arg(app_icon_pos_parts[0])
-        .arg(app_icon_pos_parts[1])
+        .args(layout.app_icon_position.map(|v| v.to_string()))
         .arg("--app-drop-link")
-        .arg(app_drop_link_pos_parts[0])
-        .arg(app_drop_link_pos_parts[1])
+        .args(layout.app_drop_link_position.map(|v| v.to_string()))
         .arg("--app-drop-link-name")
         .arg(" ")
         .arg("--hide-extension")
@@ -7,18 +7,108 @@ pub mod icns;
 pub mod ico;
 pub mod raster;
 
-use crate::config::{BrandConfig, FileType, Transformation};
+use crate::config::{BrandConfig, EscapeMode, FileType, Transformation};
 use crate::error::{Error, Result};
 use crate::platform::PlatformCapabilities;
-use crate::utils::string_processing;
+use crate::utils::builtins::Builtins;
+use crate::utils::string_processing::{self, StringFilter};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// Per-brand replacements for files in `static/` live under this directory.
+pub const STATIC_OVERRIDES_DIR: &str = "static-overrides";
 
 pub struct TransformationContext<'a> {
-    pub source_dir: &'a Path,
+    /// Brand source directory followed by those of the brands it extends
+    pub source_dirs: &'a [PathBuf],
     pub static_dir: &'a Path,
     pub output_dir: &'a Path,
     pub brand_config: &'a BrandConfig,
     pub capabilities: &'a PlatformCapabilities,
+    pub builtins: &'a Builtins,
+    /// Static inputs that were read from a brand's `static-overrides/`,
+    /// collected so the engine can report them
+    pub static_overrides: RefCell<Vec<PathBuf>>,
+    /// Raster inputs parsed so far, so an input rendered at several sizes is
+    /// only read and parsed once
+    pub raster_sources: RefCell<HashMap<PathBuf, Rc<raster::RasterSource>>>,
+}
+
+impl<'a> TransformationContext<'a> {
+    /// Directories searched for `{{#include}}` targets, brand sources first.
+    pub fn include_dirs(&self) -> Vec<&'a Path> {
+        let mut dirs: Vec<&Path> = self.source_dirs.iter().map(PathBuf::as_path).collect();
+        dirs.push(self.static_dir);
+        dirs
+    }
+
+    /// Load a raster input, reusing it if an earlier output already did.
+    fn raster_source(&self, path: &Path) -> Result<Rc<raster::RasterSource>> {
+        if let Some(source) = self.raster_sources.borrow().get(path) {
+            return Ok(Rc::clone(source));
+        }
+        let source = Rc::new(raster::RasterSource::load(path)?);
+        self.raster_sources
+            .borrow_mut()
+            .insert(path.to_path_buf(), Rc::clone(&source));
+        Ok(source)
+    }
+
+    /// Resolve an input path against the brand sources or the shared static tree.
+    ///
+    /// Source files missing from the brand directory fall back to the brands
+    /// it extends, nearest first. Static files are looked up in each brand's
+    /// `static-overrides/` directory, in the same order, before `static/`.
+    /// `brand:<name>` files come from that sibling brand's directory only.
+    fn resolve_input_path(&self, file_type: &FileType, input_path: &str) -> Result<PathBuf> {
+        let base_dirs: Vec<PathBuf> = match file_type {
+            FileType::Source => self.source_dirs.to_vec(),
+            FileType::Brand(name) => {
+                // The last directory is always a real brand under `brands/`,
+                // even when the first belongs to a virtual brand
+                let brand_dir = self.source_dirs[self.source_dirs.len() - 1]
+                    .parent()
+                    .map(|brands_dir| brands_dir.join(name))
+                    .filter(|dir| dir.is_dir())
+                    .ok_or_else(|| {
+                        Error::Config(format!(
+                            "fileType 'brand:{}' refers to a brand that does not exist",
+                            name
+                        ))
+                    })?;
+                vec![brand_dir]
+            }
+            FileType::Static => self
+                .source_dirs
+                .iter()
+                .map(|dir| dir.join(STATIC_OVERRIDES_DIR))
+                .chain(std::iter::once(self.static_dir.to_path_buf()))
+                .collect(),
+        };
+
+        let (index, full_path) = base_dirs
+            .iter()
+            .map(|dir| dir.join(input_path))
+            .enumerate()
+            .find(|(_, path)| path.exists())
+            .ok_or_else(|| {
+                // Report the brand's own path for sources and `static/` for static files
+                let expected_dir = match file_type {
+                    FileType::Source | FileType::Brand(_) => base_dirs.first(),
+                    FileType::Static => base_dirs.last(),
+                };
+                Error::FileNotFound(expected_dir.unwrap().join(input_path))
+            })?;
+
+        if matches!(file_type, FileType::Static) && index < self.source_dirs.len() {
+            self.static_overrides.borrow_mut().push(full_path.clone());
+        }
+
+        Ok(full_path)
+    }
 }
 
 pub fn execute(transformation: &Transformation, ctx: &TransformationContext) -> Result<()> {
@@ -36,13 +126,22 @@ pub fn execute(transformation: &Transformation, ctx: &TransformationContext) ->
             offset_x,
             offset_y,
             fit,
+            ..
         } => {
-            let resolved_input_path =
-                resolve_input_path(file_type, input_path, ctx.source_dir, ctx.static_dir)?;
+            // `expand` gives every raster it returns a single output
+            let (Some(output_path), Some(width), Some(height)) = (output_path, width, height)
+            else {
+                return Err(Error::Transformation(format!(
+                    "raster of '{}' has no single output to render",
+                    input_path
+                )));
+            };
+            let resolved_input_path = ctx.resolve_input_path(file_type, input_path)?;
             let resolved_output_path = ctx.output_dir.join(output_path);
+            let source = ctx.raster_source(&resolved_input_path)?;
 
             raster::execute(
-                &resolved_input_path,
+                &source,
                 &resolved_output_path,
                 output_file_type,
                 *width,
@@ -61,8 +160,7 @@ pub fn execute(transformation: &Transformation, ctx: &TransformationContext) ->
             output_path,
             sizes,
         } => {
-            let resolved_input_path =
-                resolve_input_path(file_type, input_path, ctx.source_dir, ctx.static_dir)?;
+            let resolved_input_path = ctx.resolve_input_path(file_type, input_path)?;
             let resolved_output_path = ctx.output_dir.join(output_path);
 
             ico::execute(&resolved_input_path, &resolved_output_path, sizes)
@@ -80,8 +178,7 @@ pub fn execute(transformation: &Transformation, ctx: &TransformationContext) ->
                 ));
             }
 
-            let resolved_input_path =
-                resolve_input_path(file_type, input_path, ctx.source_dir, ctx.static_dir)?;
+            let resolved_input_path = ctx.resolve_input_path(file_type, input_path)?;
             let resolved_output_path = ctx.output_dir.join(output_path);
 
             icns::execute(&resolved_input_path, &resolved_output_path, sizes)
@@ -102,23 +199,13 @@ pub fn execute(transformation: &Transformation, ctx: &TransformationContext) ->
                 ));
             }
 
-            let resolved_liquid_glass_icon_path = resolve_input_path(
-                liquid_glass_icon_file_type,
-                liquid_glass_icon_path,
-                ctx.source_dir,
-                ctx.static_dir,
-            )?;
+            let resolved_liquid_glass_icon_path =
+                ctx.resolve_input_path(liquid_glass_icon_file_type, liquid_glass_icon_path)?;
             let resolved_output_path = ctx.output_dir.join(output_path);
 
-            let app_icon_path = resolve_input_path(
-                app_icon_file_type,
-                app_icon_input,
-                ctx.source_dir,
-                ctx.static_dir,
-            )?;
+            let app_icon_path = ctx.resolve_input_path(app_icon_file_type, app_icon_input)?;
 
-            let icon_path_input =
-                resolve_input_path(icon_file_type, icon_input, ctx.source_dir, ctx.static_dir)?;
+            let icon_path_input = ctx.resolve_input_path(icon_file_type, icon_input)?;
 
             assets_car::execute(
                 &resolved_liquid_glass_icon_path,
@@ -133,8 +220,7 @@ pub fn execute(transformation: &Transformation, ctx: &TransformationContext) ->
             input_path,
             output_path,
         } => {
-            let resolved_input_path =
-                resolve_input_path(file_type, input_path, ctx.source_dir, ctx.static_dir)?;
+            let resolved_input_path = ctx.resolve_input_path(file_type, input_path)?;
             let resolved_output_path = ctx.output_dir.join(output_path);
 
             copy::execute(&resolved_input_path, &resolved_output_path)
@@ -144,15 +230,31 @@ pub fn execute(transformation: &Transformation, ctx: &TransformationContext) ->
             file_type,
             input_path,
             output_path,
+            dialect,
+            escape,
+            encoding,
+            line_endings,
+            locale,
+            ..
         } => {
-            let resolved_input_path =
-                resolve_input_path(file_type, input_path, ctx.source_dir, ctx.static_dir)?;
+            let resolved_input_path = ctx.resolve_input_path(file_type, input_path)?;
             let resolved_output_path = ctx.output_dir.join(output_path);
+            let include_dirs = ctx.include_dirs();
+            let brand_config = localized_brand_config(ctx.brand_config, locale);
+            let options = copy_preprocess::PreprocessOptions {
+                brand_config: &brand_config,
+                include_dirs: &include_dirs,
+                default_escape: resolve_escape(escape, output_path),
+                dialect: *dialect,
+                builtins: ctx.builtins,
+            };
 
             copy_preprocess::execute(
                 &resolved_input_path,
                 &resolved_output_path,
-                ctx.brand_config,
+                &options,
+                encoding,
+                line_endings,
             )
         }
 
@@ -168,8 +270,7 @@ pub fn execute(transformation: &Transformation, ctx: &TransformationContext) ->
                 ));
             }
 
-            let resolved_input_path =
-                resolve_input_path(file_type, input_path, ctx.source_dir, ctx.static_dir)?;
+            let resolved_input_path = ctx.resolve_input_path(file_type, input_path)?;
             let resolved_output_path = ctx.output_dir.join(output_path);
 
             copy_image_mac::execute(&resolved_input_path, &resolved_output_path, *dpi)
@@ -210,34 +311,20 @@ pub fn execute(transformation: &Transformation, ctx: &TransformationContext) ->
                 string_processing::process_string_replacements(app_name, ctx.brand_config)?;
             let processed_volume_name =
                 string_processing::process_string_replacements(volume_name, ctx.brand_config)?;
-            let processed_window_position =
-                string_processing::process_string_replacements(window_position, ctx.brand_config)?;
-            let processed_window_size =
-                string_processing::process_string_replacements(window_size, ctx.brand_config)?;
-            let processed_app_icon_position = string_processing::process_string_replacements(
+            let layout = dmg_layout(
+                window_position,
+                window_size,
                 app_icon_position,
-                ctx.brand_config,
-            )?;
-            let processed_app_drop_link_position = string_processing::process_string_replacements(
                 app_drop_link_position,
                 ctx.brand_config,
             )?;
 
             let resolved_output_path = ctx.output_dir.join(output_path);
 
-            let background_image_path = resolve_input_path(
-                background_image_file_type,
-                background_image,
-                ctx.source_dir,
-                ctx.static_dir,
-            )?;
+            let background_image_path =
+                ctx.resolve_input_path(background_image_file_type, background_image)?;
 
-            let volume_icon_path = resolve_input_path(
-                volume_icon_file_type,
-                volume_icon,
-                ctx.source_dir,
-                ctx.static_dir,
-            )?;
+            let volume_icon_path = ctx.resolve_input_path(volume_icon_file_type, volume_icon)?;
 
             dsstore::execute(
                 &resolved_output_path,
@@ -245,10 +332,7 @@ pub fn execute(transformation: &Transformation, ctx: &TransformationContext) ->
                 &processed_volume_name,
                 &background_image_path,
                 &volume_icon_path,
-                &processed_window_position,
-                &processed_window_size,
-                &processed_app_icon_position,
-                &processed_app_drop_link_position,
+                &layout,
             )
         }
     }
@@ -256,10 +340,11 @@ pub fn execute(transformation: &Transformation, ctx: &TransformationContext) ->
 
 /// Validate a transformation without executing it.
 ///
-/// Resolves every input path (errors if any referenced file is missing) and
-/// expands template strings (errors on malformed templates or unknown
-/// `{{#str key}}` references). Skips platform tool checks and never writes
-/// to the filesystem — safe to run on Linux CI for macOS transformations.
+/// Resolves every input path (errors if any referenced file is missing,
+/// including files pulled in by `{{#include}}`) and expands template strings
+/// (errors on malformed templates or unknown `{{#str key}}` references).
+/// Skips platform tool checks and never writes to the filesystem — safe to
+/// run on Linux CI for macOS transformations.
 pub fn validate(transformation: &Transformation, ctx: &TransformationContext) -> Result<()> {
     match transformation {
         Transformation::Raster {
@@ -282,17 +367,34 @@ pub fn validate(transformation: &Transformation, ctx: &TransformationContext) ->
             input_path,
             ..
         }
-        | Transformation::CopyPreprocess {
+        | Transformation::CopyImageMac {
             file_type,
             input_path,
             ..
+        } => {
+            ctx.resolve_input_path(file_type, input_path)?;
         }
-        | Transformation::CopyImageMac {
+
+        Transformation::CopyPreprocess {
             file_type,
             input_path,
+            output_path,
+            dialect,
+            escape,
+            locale,
             ..
         } => {
-            resolve_input_path(file_type, input_path, ctx.source_dir, ctx.static_dir)?;
+            let resolved_input_path = ctx.resolve_input_path(file_type, input_path)?;
+            let include_dirs = ctx.include_dirs();
+            let brand_config = localized_brand_config(ctx.brand_config, locale);
+            let options = copy_preprocess::PreprocessOptions {
+                brand_config: &brand_config,
+                include_dirs: &include_dirs,
+                default_escape: resolve_escape(escape, output_path),
+                dialect: *dialect,
+                builtins: ctx.builtins,
+            };
+            copy_preprocess::validate(&resolved_input_path, Path::new(output_path), &options)?;
         }
 
         Transformation::AssetsCar {
@@ -304,19 +406,9 @@ pub fn validate(transformation: &Transformation, ctx: &TransformationContext) ->
             icon_file_type,
             ..
         } => {
-            resolve_input_path(
-                liquid_glass_icon_file_type,
-                liquid_glass_icon_path,
-                ctx.source_dir,
-                ctx.static_dir,
-            )?;
-            resolve_input_path(
-                app_icon_file_type,
-                app_icon_input,
-                ctx.source_dir,
-                ctx.static_dir,
-            )?;
-            resolve_input_path(icon_file_type, icon_input, ctx.source_dir, ctx.static_dir)?;
+            ctx.resolve_input_path(liquid_glass_icon_file_type, liquid_glass_icon_path)?;
+            ctx.resolve_input_path(app_icon_file_type, app_icon_input)?;
+            ctx.resolve_input_path(icon_file_type, icon_input)?;
         }
 
         Transformation::DsStore {
@@ -332,49 +424,525 @@ pub fn validate(transformation: &Transformation, ctx: &TransformationContext) ->
             app_drop_link_position,
             ..
         } => {
-            for template in [
-                app_name,
-                volume_name,
+            for template in [app_name, volume_name] {
+                string_processing::process_string_replacements(template, ctx.brand_config)?;
+            }
+            dmg_layout(
                 window_position,
                 window_size,
                 app_icon_position,
                 app_drop_link_position,
-            ] {
-                string_processing::process_string_replacements(template, ctx.brand_config)?;
-            }
-            resolve_input_path(
-                background_image_file_type,
-                background_image,
-                ctx.source_dir,
-                ctx.static_dir,
-            )?;
-            resolve_input_path(
-                volume_icon_file_type,
-                volume_icon,
-                ctx.source_dir,
-                ctx.static_dir,
+                ctx.brand_config,
             )?;
+            ctx.resolve_input_path(background_image_file_type, background_image)?;
+            ctx.resolve_input_path(volume_icon_file_type, volume_icon)?;
+        }
+    }
+    Ok(())
+}
+
+/// The transformations a config entry actually runs: `{{#str key}}` and
+/// `{{#env key}}` are expanded in every path field, a raster with `outputs`
+/// or `scales` becomes one raster per output and scale, and a
+/// `forEachLocale` entry becomes one entry per locale.
+pub fn expand(
+    transformation: &Transformation,
+    brand_config: &BrandConfig,
+) -> Result<Vec<Transformation>> {
+    let mut expanded = transformation.clone();
+    for path in expanded.paths_mut() {
+        *path = string_processing::expand_path_placeholders(path, brand_config)?;
+    }
+    let mut scaled = Vec::new();
+    for output in expand_outputs(expanded)? {
+        scaled.extend(expand_scales(output)?);
+    }
+    let mut localized = Vec::new();
+    for output in scaled {
+        localized.extend(expand_locales(output, brand_config)?);
+    }
+    Ok(localized)
+}
+
+/// A `forEachLocale` entry becomes one entry per locale, each writing to its
+/// own locale directory.
+fn expand_locales(
+    transformation: Transformation,
+    brand_config: &BrandConfig,
+) -> Result<Vec<Transformation>> {
+    let Transformation::CopyPreprocess {
+        ref output_path,
+        for_each_locale: true,
+        ..
+    } = transformation
+    else {
+        return Ok(vec![transformation]);
+    };
+
+    let targets = copy_preprocess::locale_targets(output_path, brand_config)?;
+    Ok(targets
+        .into_iter()
+        .map(|(target_locale, target_path)| {
+            let mut single = transformation.clone();
+            if let Transformation::CopyPreprocess {
+                output_path,
+                for_each_locale,
+                locale,
+                ..
+            } = &mut single
+            {
+                *output_path = target_path;
+                *for_each_locale = false;
+                *locale = Some(target_locale);
+            }
+            single
+        })
+        .collect())
+}
+
+/// The brand config to preprocess with: the locale's string overrides applied
+/// for an entry expanded from `forEachLocale`, otherwise the brand's own.
+fn localized_brand_config<'a>(
+    brand_config: &'a BrandConfig,
+    locale: &Option<String>,
+) -> Cow<'a, BrandConfig> {
+    match locale {
+        Some(locale) => Cow::Owned(brand_config.for_locale(locale)),
+        None => Cow::Borrowed(brand_config),
+    }
+}
+
+fn expand_outputs(transformation: Transformation) -> Result<Vec<Transformation>> {
+    transformation.check_outputs()?;
+    let Transformation::Raster {
+        outputs: Some(ref outputs),
+        ..
+    } = transformation
+    else {
+        return Ok(vec![transformation]);
+    };
+
+    let mut expanded = Vec::with_capacity(outputs.len());
+    for output in outputs {
+        let mut single = transformation.clone();
+        if let Transformation::Raster {
+            output_path,
+            width,
+            height,
+            padding_pixels_width,
+            padding_pixels_height,
+            offset_x,
+            offset_y,
+            outputs,
+            ..
+        } = &mut single
+        {
+            *output_path = Some(output.output_path.clone());
+            *width = Some(output.width);
+            *height = Some(output.height);
+            *padding_pixels_width = output.padding_pixels_width.or(*padding_pixels_width);
+            *padding_pixels_height = output.padding_pixels_height.or(*padding_pixels_height);
+            *offset_x = output.offset_x.or(*offset_x);
+            *offset_y = output.offset_y.or(*offset_y);
+            *outputs = None;
         }
+        expanded.push(single);
+    }
+    Ok(expanded)
+}
+
+fn expand_scales(transformation: Transformation) -> Result<Vec<Transformation>> {
+    let Transformation::Raster {
+        scales: Some(ref scales),
+        ..
+    } = transformation
+    else {
+        return Ok(vec![transformation]);
+    };
+
+    if scales.is_empty() || scales.contains(&0) {
+        return Err(Error::Config(format!(
+            "raster '{}': scales must be a non-empty list of positive integers, got {:?}",
+            transformation.output_path(),
+            scales
+        )));
+    }
+
+    let mut expanded = Vec::with_capacity(scales.len());
+    for &scale in scales {
+        let mut scaled = transformation.clone();
+        if let Transformation::Raster {
+            output_path,
+            width,
+            height,
+            padding_pixels_width,
+            padding_pixels_height,
+            offset_x,
+            offset_y,
+            scales,
+            ..
+        } = &mut scaled
+        {
+            let too_large = || {
+                Error::Config(format!(
+                    "raster '{}': scale {} makes its size, padding or offsets too large",
+                    transformation.output_path(),
+                    scale
+                ))
+            };
+            for size in [width, height, padding_pixels_width, padding_pixels_height]
+                .into_iter()
+                .flatten()
+            {
+                *size = size.checked_mul(scale).ok_or_else(too_large)?;
+            }
+            let offset_scale = i32::try_from(scale).map_err(|_| too_large())?;
+            for offset in [offset_x, offset_y].into_iter().flatten() {
+                *offset = offset.checked_mul(offset_scale).ok_or_else(too_large)?;
+            }
+            *output_path = output_path
+                .as_deref()
+                .map(|path| scaled_output_path(path, scale));
+            *scales = None;
+        }
+        expanded.push(scaled);
+    }
+    Ok(expanded)
+}
+
+/// `content/about-logo.png` at scale 2 is `content/about-logo@2x.png`.
+pub(crate) fn scaled_output_path(output_path: &str, scale: u32) -> String {
+    if scale == 1 {
+        return output_path.to_string();
+    }
+    let file_start = output_path.rfind('/').map_or(0, |i| i + 1);
+    match output_path[file_start..].rfind('.') {
+        Some(dot) if dot > 0 => {
+            let dot = file_start + dot;
+            format!("{}@{}x{}", &output_path[..dot], scale, &output_path[dot..])
+        }
+        _ => format!("{}@{}x", output_path, scale),
+    }
+}
+
+/// Check the typed fields of a transformation against the brand config.
+///
+/// Run when the configuration is loaded, so a brand value of the wrong shape
+/// is reported even if the transformation using it is skipped on this host.
+pub fn check_typed_fields(
+    transformation: &Transformation,
+    brand_config: &BrandConfig,
+) -> Result<()> {
+    if let Transformation::DsStore {
+        window_position,
+        window_size,
+        app_icon_position,
+        app_drop_link_position,
+        ..
+    } = transformation
+    {
+        dmg_layout(
+            window_position,
+            window_size,
+            app_icon_position,
+            app_drop_link_position,
+            brand_config,
+        )?;
     }
     Ok(())
 }
 
-fn resolve_input_path(
-    file_type: &FileType,
-    input_path: &str,
-    source_dir: &Path,
-    static_dir: &Path,
-) -> Result<PathBuf> {
-    let base_dir = match file_type {
-        FileType::Source => source_dir,
-        FileType::Static => static_dir,
+fn dmg_layout(
+    window_position: &str,
+    window_size: &str,
+    app_icon_position: &str,
+    app_drop_link_position: &str,
+    brand_config: &BrandConfig,
+) -> Result<dsstore::DmgLayout> {
+    let field = |name: &str, template: &str| {
+        string_processing::process_typed::<[u32; 2]>(template, brand_config).map_err(|e| match e {
+            Error::Config(message) => Error::Config(format!("ds-store {}: {}", name, message)),
+            other => other,
+        })
     };
 
-    let full_path = base_dir.join(input_path);
+    Ok(dsstore::DmgLayout {
+        window_position: field("windowPosition", window_position)?,
+        window_size: field("windowSize", window_size)?,
+        app_icon_position: field("appIconPosition", app_icon_position)?,
+        app_drop_link_position: field("appDropLinkPosition", app_drop_link_position)?,
+    })
+}
+
+/// Turn a configured escape mode into the filter applied to `{{#str}}` values.
+fn resolve_escape(escape: &EscapeMode, output_path: &str) -> Option<StringFilter> {
+    match escape {
+        EscapeMode::Auto => StringFilter::for_output_path(Path::new(output_path)),
+        EscapeMode::None => None,
+        EscapeMode::Xml => Some(StringFilter::Xml),
+        EscapeMode::Nsis => Some(StringFilter::Nsis),
+        EscapeMode::Properties => Some(StringFilter::Properties),
+        EscapeMode::Fluent => Some(StringFilter::Fluent),
+        EscapeMode::Json => Some(StringFilter::Json),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp::TempDir;
+    use std::fs;
+
+    #[test]
+    fn test_resolve_input_path() {
+        let root = TempDir::new("resolve-input").unwrap();
+        let static_dir = root.create_dir("static").unwrap();
+        let official = root.create_dir("brands/official").unwrap();
+        let beta = root.create_dir("brands/beta").unwrap();
+        fs::write(static_dir.join("moz.build"), "shared").unwrap();
+        fs::write(static_dir.join("jar.mn"), "shared").unwrap();
+        fs::create_dir_all(official.join("static-overrides")).unwrap();
+        fs::write(official.join("static-overrides/moz.build"), "official").unwrap();
+        fs::write(official.join("logo.svg"), "<svg/>").unwrap();
+
+        let source_dirs = [beta.clone(), official.clone()];
+        let brand_config = BrandConfig::default();
+        let capabilities = PlatformCapabilities::default();
+        let builtins = Builtins::new("beta", 0);
+        let ctx = TransformationContext {
+            source_dirs: &source_dirs,
+            static_dir: &static_dir,
+            output_dir: root.path(),
+            brand_config: &brand_config,
+            capabilities: &capabilities,
+            builtins: &builtins,
+            static_overrides: RefCell::new(Vec::new()),
+            raster_sources: RefCell::new(HashMap::new()),
+        };
+
+        // Source files fall back to the parent brand
+        assert_eq!(
+            ctx.resolve_input_path(&FileType::Source, "logo.svg")
+                .unwrap(),
+            official.join("logo.svg")
+        );
+
+        // Inherited static overrides win over static/, and are recorded
+        assert_eq!(
+            ctx.resolve_input_path(&FileType::Static, "moz.build")
+                .unwrap(),
+            official.join("static-overrides/moz.build")
+        );
+        assert_eq!(
+            ctx.resolve_input_path(&FileType::Static, "jar.mn").unwrap(),
+            static_dir.join("jar.mn")
+        );
+        assert_eq!(
+            *ctx.static_overrides.borrow(),
+            [official.join("static-overrides/moz.build")]
+        );
+
+        // brand:<name> reads from a sibling brand without inheritance
+        let nightly = root.create_dir("brands/nightly").unwrap();
+        fs::write(nightly.join("document-pdf.svg"), "<svg/>").unwrap();
+        assert_eq!(
+            ctx.resolve_input_path(&FileType::Brand("nightly".to_string()), "document-pdf.svg")
+                .unwrap(),
+            nightly.join("document-pdf.svg")
+        );
+        let err = ctx
+            .resolve_input_path(&FileType::Brand("aurora".to_string()), "logo.svg")
+            .unwrap_err();
+        assert!(err.to_string().contains("'brand:aurora'"), "{}", err);
+        assert!(matches!(
+            ctx.resolve_input_path(&FileType::Brand("nightly".to_string()), "logo.svg"),
+            Err(Error::FileNotFound(_))
+        ));
+
+        match ctx.resolve_input_path(&FileType::Static, "missing.txt") {
+            Err(Error::FileNotFound(path)) => assert_eq!(path, static_dir.join("missing.txt")),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
 
-    if !full_path.exists() {
-        return Err(Error::FileNotFound(full_path));
+    #[test]
+    fn test_file_type_parsing() {
+        let parse = |value: &str| serde_json::from_value::<FileType>(serde_json::json!(value));
+        assert_eq!(parse("source").unwrap(), FileType::Source);
+        assert_eq!(parse("static").unwrap(), FileType::Static);
+        assert_eq!(
+            parse("brand:nightly").unwrap(),
+            FileType::Brand("nightly".to_string())
+        );
+        assert!(parse("brand:../static").is_err());
+        assert!(parse("brand:").is_err());
+        assert!(parse("sources").is_err());
+        assert_eq!(
+            serde_json::to_value(FileType::Brand("nightly".to_string())).unwrap(),
+            "brand:nightly"
+        );
     }
 
-    Ok(full_path)
+    #[test]
+    fn test_expand_scales() {
+        let transformation: Transformation = serde_json::from_str(
+            r#"{ "type": "raster", "fileType": "source", "inputPath": "logo.svg",
+                 "outputPath": "content/about-logo.png", "outputFileType": "png",
+                 "width": 192, "height": 96, "paddingPixelsWidth": 4, "offsetY": -2,
+                 "scales": [1, 2] }"#,
+        )
+        .unwrap();
+
+        let expanded = expand(&transformation, &BrandConfig::default()).unwrap();
+        let summary: Vec<_> = expanded
+            .iter()
+            .map(|t| match t {
+                Transformation::Raster {
+                    output_path,
+                    width,
+                    height,
+                    padding_pixels_width,
+                    offset_y,
+                    scales,
+                    ..
+                } => (
+                    output_path.as_deref().unwrap(),
+                    width.unwrap(),
+                    height.unwrap(),
+                    *padding_pixels_width,
+                    *offset_y,
+                    scales.is_none(),
+                ),
+                other => panic!("unexpected transformation {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("content/about-logo.png", 192, 96, Some(4), Some(-2), true),
+                (
+                    "content/about-logo@2x.png",
+                    384,
+                    192,
+                    Some(8),
+                    Some(-4),
+                    true
+                ),
+            ]
+        );
+
+        assert_eq!(scaled_output_path("a.b/icon", 3), "a.b/icon@3x");
+        assert_eq!(scaled_output_path(".hidden", 2), ".hidden@2x");
+
+        // Scales that overflow the size or offsets are reported, not wrapped
+        for (fields, scale) in [
+            (r#""width": 16, "height": 16"#, 4294967295u32),
+            (r#""width": 16, "height": 16, "offsetX": 2"#, 2147483648),
+        ] {
+            let transformation: Transformation = serde_json::from_str(&format!(
+                r#"{{ "type": "raster", "fileType": "source", "inputPath": "logo.svg",
+                     "outputPath": "icon.png", "outputFileType": "png", {},
+                     "scales": [{}] }}"#,
+                fields, scale
+            ))
+            .unwrap();
+            let err = expand(&transformation, &BrandConfig::default()).unwrap_err();
+            assert!(
+                err.to_string()
+                    .contains(&format!("raster 'icon.png': scale {}", scale)),
+                "{}",
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn test_expand_locales() {
+        let transformation: Transformation = serde_json::from_str(
+            r#"{ "type": "copy-preprocess", "fileType": "source", "inputPath": "brand.ftl",
+                 "outputPath": "locales/en-US/brand.ftl", "forEachLocale": true }"#,
+        )
+        .unwrap();
+        let brand_config = BrandConfig {
+            locales: [("de".to_string(), HashMap::new())].into_iter().collect(),
+            ..Default::default()
+        };
+
+        let expanded = expand(&transformation, &brand_config).unwrap();
+        let summary: Vec<_> = expanded
+            .iter()
+            .map(|t| match t {
+                Transformation::CopyPreprocess {
+                    output_path,
+                    for_each_locale,
+                    locale,
+                    ..
+                } => (output_path.as_str(), *for_each_locale, locale.as_deref()),
+                other => panic!("unexpected transformation {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("locales/en-US/brand.ftl", false, Some("en-US")),
+                ("locales/de/brand.ftl", false, Some("de")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_expand_outputs() {
+        let transformation: Transformation = serde_json::from_str(
+            r#"{ "type": "raster", "fileType": "source", "inputPath": "logo.svg",
+                 "outputFileType": "png", "paddingPixelsWidth": 1, "scales": [1, 2],
+                 "outputs": [
+                     { "outputPath": "default16.png", "width": 16, "height": 16 },
+                     { "outputPath": "default{{#env size}}.png", "width": 256, "height": 256,
+                       "paddingPixelsWidth": 8 }
+                 ] }"#,
+        )
+        .unwrap();
+        let brand_config = BrandConfig {
+            env: HashMap::from([("size".to_string(), "256".to_string())]),
+            ..Default::default()
+        };
+
+        let expanded = expand(&transformation, &brand_config).unwrap();
+        let summary: Vec<_> = expanded
+            .iter()
+            .map(|t| match t {
+                Transformation::Raster {
+                    output_path,
+                    width,
+                    padding_pixels_width,
+                    outputs,
+                    ..
+                } => (
+                    output_path.as_deref().unwrap(),
+                    width.unwrap(),
+                    *padding_pixels_width,
+                    outputs.is_none(),
+                ),
+                other => panic!("unexpected transformation {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("default16.png", 16, Some(1), true),
+                ("default16@2x.png", 32, Some(2), true),
+                ("default256.png", 256, Some(8), true),
+                ("default256@2x.png", 512, Some(16), true),
+            ]
+        );
+
+        // outputPath, width and height can't also be set on the entry
+        let transformation: Transformation = serde_json::from_str(
+            r#"{ "type": "raster", "fileType": "source", "inputPath": "logo.svg",
+                 "outputFileType": "png", "width": 16,
+                 "outputs": [{ "outputPath": "a.png", "width": 16, "height": 16 }] }"#,
+        )
+        .unwrap();
+        assert!(expand(&transformation, &brand_config).is_err());
+    }
 }
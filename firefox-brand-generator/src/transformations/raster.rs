@@ -1,11 +1,30 @@
 use crate::config::{FitStrategy, OutputFileType};
 use crate::error::Result;
 use crate::image_processing::{self, ImageSource};
-use image::GenericImageView;
+use image::{DynamicImage, GenericImageView};
+use resvg::usvg;
 use std::path::Path;
 
+/// A raster input, parsed once and reused for every size it's rendered at.
+pub enum RasterSource {
+    Svg(Box<usvg::Tree>),
+    Raster(DynamicImage),
+}
+
+impl RasterSource {
+    pub fn load(input_path: &Path) -> Result<Self> {
+        Ok(match image_processing::load(input_path)? {
+            ImageSource::Svg(svg_data) => {
+                RasterSource::Svg(Box::new(image_processing::parse_svg(&svg_data)?))
+            }
+            ImageSource::Raster(img) => RasterSource::Raster(img),
+        })
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn execute(
-    input_path: &Path,
+    source: &RasterSource,
     output_path: &Path,
     output_file_type: &OutputFileType,
     width: u32,
@@ -24,21 +43,18 @@ pub fn execute(
     let content_width = width.saturating_sub(pad_w * 2);
     let content_height = height.saturating_sub(pad_h * 2);
 
-    // Load the image (SVG or raster)
-    let img_source = image_processing::load(input_path)?;
-
     // Convert to raster if needed, using the content dimensions
-    let img = match img_source {
-        ImageSource::Svg(svg_data) => {
+    let img = match source {
+        RasterSource::Svg(tree) => {
             // For SVG, we rasterize with the FitStrategy applied directly
-            image_processing::rasterize_svg(&svg_data, content_width, content_height, fit)?
+            image_processing::render_svg(tree, content_width, content_height, fit)?
         }
-        ImageSource::Raster(img) => {
+        RasterSource::Raster(img) => {
             // For raster images, we need to handle different fit strategies
             match fit {
                 FitStrategy::Fill => {
                     // Stretch to exact dimensions
-                    image_processing::resize(&img, content_width, content_height)?
+                    image_processing::resize(img, content_width, content_height)?
                 }
                 FitStrategy::Contain | FitStrategy::ScaleDown => {
                     // Maintain aspect ratio
@@ -0,0 +1,97 @@
+use crate::error::{Error, Result};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Read-only values available to templates as `{{#builtin name}}`.
+#[derive(Debug, Clone)]
+pub struct Builtins {
+    brand: String,
+    source_date_epoch: u64,
+}
+
+impl Builtins {
+    pub fn new(brand: &str, source_date_epoch: u64) -> Self {
+        Self {
+            brand: brand.to_string(),
+            source_date_epoch,
+        }
+    }
+
+    /// Build the values for `brand`, taking the build time from
+    /// `SOURCE_DATE_EPOCH` when it is set so output stays reproducible.
+    pub fn from_environment(brand: &str) -> Result<Self> {
+        let source_date_epoch = match std::env::var("SOURCE_DATE_EPOCH") {
+            Ok(value) => value.trim().parse::<u64>().map_err(|_| {
+                Error::Config(format!(
+                    "SOURCE_DATE_EPOCH must be a number of seconds, got '{}'",
+                    value
+                ))
+            })?,
+            Err(_) => SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        };
+
+        Ok(Self::new(brand, source_date_epoch))
+    }
+
+    pub fn get(&self, name: &str) -> Option<String> {
+        match name {
+            "year" => Some(year_from_epoch(self.source_date_epoch).to_string()),
+            "generatorVersion" => Some(env!("CARGO_PKG_VERSION").to_string()),
+            "brand" => Some(self.brand.clone()),
+            "sourceDateEpoch" => Some(self.source_date_epoch.to_string()),
+            _ => None,
+        }
+    }
+
+    pub fn names() -> &'static [&'static str] {
+        &["year", "generatorVersion", "brand", "sourceDateEpoch"]
+    }
+}
+
+/// Gregorian year (UTC) of a Unix timestamp.
+fn year_from_epoch(seconds: u64) -> i64 {
+    // Howard Hinnant's days-to-civil algorithm
+    let days = (seconds / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let year = year_of_era + era * 400;
+    // Years are counted from March, so January and February belong to the next one
+    if month_index >= 10 { year + 1 } else { year }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_year_from_epoch() {
+        assert_eq!(year_from_epoch(0), 1970);
+        // 2000-02-29T00:00:00Z
+        assert_eq!(year_from_epoch(951_782_400), 2000);
+        // 2024-12-31T23:59:59Z and one second later
+        assert_eq!(year_from_epoch(1_735_689_599), 2024);
+        assert_eq!(year_from_epoch(1_735_689_600), 2025);
+    }
+
+    #[test]
+    fn test_builtin_values() {
+        let builtins = Builtins::new("nightly", 1_735_689_600);
+        assert_eq!(builtins.get("year").as_deref(), Some("2025"));
+        assert_eq!(builtins.get("brand").as_deref(), Some("nightly"));
+        assert_eq!(
+            builtins.get("sourceDateEpoch").as_deref(),
+            Some("1735689600")
+        );
+        assert_eq!(
+            builtins.get("generatorVersion").as_deref(),
+            Some(env!("CARGO_PKG_VERSION"))
+        );
+        assert_eq!(builtins.get("unknown"), None);
+    }
+}
@@ -0,0 +1,272 @@
+use crate::config::types::BrandConfig;
+use crate::error::{Error, Result};
+use owo_colors::OwoColorize;
+use regex::Regex;
+
+// Look up a condition variable. `env.` and `strings.` prefixes pick the map
+// explicitly; bare names resolve to env for backward compatibility.
+fn resolve_condition_variable<'a>(
+    var_name: &str,
+    brand_config: &'a BrandConfig,
+) -> Option<&'a String> {
+    if let Some(key) = var_name.strip_prefix("env.") {
+        brand_config.env.get(key)
+    } else if let Some(key) = var_name.strip_prefix("strings.") {
+        brand_config.strings.get(key)
+    } else {
+        brand_config.env.get(var_name)
+    }
+}
+
+// Helper function to evaluate a single condition
+fn evaluate_single_condition(
+    var_name: &str,
+    operator: &str,
+    expected_value: &str,
+    brand_config: &BrandConfig,
+) -> bool {
+    resolve_condition_variable(var_name, brand_config).is_some_and(|value| {
+        match operator {
+            "==" => value == expected_value,
+            "!=" => value != expected_value,
+            _ => false, // Unsupported operator
+        }
+    })
+}
+
+/// Evaluate a `{{#if}}`-style condition such as `name != official && channel == beta`
+/// against a brand's `env` and `strings`. Unparseable expressions are false.
+pub fn evaluate_condition_expression(expression: &str, brand_config: &BrandConfig) -> bool {
+    evaluate(expression, brand_config).unwrap_or_else(|| {
+        println!(
+            "{} Invalid condition expression: '{}'",
+            "Warning:".yellow().bold(),
+            expression.trim().yellow()
+        );
+        false
+    })
+}
+
+/// Check that a condition parses, without evaluating it for any brand.
+pub fn check_condition_expression(expression: &str) -> Result<()> {
+    if expression.trim().is_empty() || evaluate(expression, &BrandConfig::default()).is_none() {
+        return Err(Error::Config(format!(
+            "Invalid condition expression: '{}'",
+            expression
+        )));
+    }
+    Ok(())
+}
+
+/// Split `expression` at every `operator` outside parentheses and quoted
+/// values.
+fn split_top_level<'a>(expression: &'a str, operator: &str) -> Vec<&'a str> {
+    let mut parts = Vec::new();
+    let mut paren_depth = 0;
+    let mut quote = None;
+    let mut part_start = 0;
+    let mut chars = expression.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(') => paren_depth += 1,
+            (None, ')') => paren_depth -= 1,
+            (None, _) if paren_depth == 0 && expression[i..].starts_with(operator) => {
+                parts.push(&expression[part_start..i]);
+                part_start = i + operator.len();
+                // Skip the rest of the operator
+                for _ in 1..operator.chars().count() {
+                    chars.next();
+                }
+            }
+            _ => {}
+        }
+    }
+    parts.push(&expression[part_start..]);
+
+    parts
+}
+
+/// The value of the condition, or `None` if any part of it doesn't parse.
+/// Every part is evaluated, so a malformed part is found even when the
+/// parts before it already decide the result.
+fn evaluate(expression: &str, brand_config: &BrandConfig) -> Option<bool> {
+    // Trim whitespace from the expression
+    let expression = expression.trim();
+
+    // Early return for empty expressions
+    if expression.is_empty() {
+        return Some(false);
+    }
+
+    // Check if the entire expression is wrapped in parentheses
+    if expression.starts_with('(') && expression.ends_with(')') {
+        let inner = &expression[1..expression.len() - 1].trim();
+        if !inner.is_empty() {
+            // Make sure the parentheses are balanced
+            let mut depth = 0;
+            let mut balanced = true;
+
+            for c in inner.chars() {
+                if c == '(' {
+                    depth += 1;
+                } else if c == ')' {
+                    depth -= 1;
+                    if depth < 0 {
+                        balanced = false;
+                        break;
+                    }
+                }
+            }
+
+            if balanced && depth == 0 {
+                // If parentheses are balanced, evaluate the inner expression
+                return evaluate(inner, brand_config);
+            }
+        }
+    }
+
+    // Handle OR (||) operator - lowest precedence
+    let parts = split_top_level(expression, "||");
+    if parts.len() > 1 {
+        // An operator with nothing on one side doesn't parse
+        let results: Option<Vec<bool>> = parts
+            .iter()
+            .map(|part| match part.trim() {
+                "" => None,
+                part => evaluate(part, brand_config),
+            })
+            .collect();
+        return results.map(|results| results.contains(&true));
+    }
+
+    // Handle AND (&&) operator - higher precedence than OR
+    let parts = split_top_level(expression, "&&");
+    if parts.len() > 1 {
+        // An operator with nothing on one side doesn't parse
+        let results: Option<Vec<bool>> = parts
+            .iter()
+            .map(|part| match part.trim() {
+                "" => None,
+                part => evaluate(part, brand_config),
+            })
+            .collect();
+        return results.map(|results| !results.contains(&false));
+    }
+
+    // Handle basic condition (var == value or var != value); the value may be
+    // quoted to compare against text containing spaces
+    let re = Regex::new(r#"^\s*([^\s=!]+)\s*(==|!=)\s*("[^"]*"|'[^']*'|[^\s"']+)\s*$"#).unwrap();
+    if let Some(caps) = re.captures(expression) {
+        let var_name = caps.get(1).map_or("", |m| m.as_str());
+        let operator = caps.get(2).map_or("", |m| m.as_str());
+        let expected_value = caps.get(3).map_or("", |m| m.as_str());
+        let expected_value = expected_value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .or_else(|| {
+                expected_value
+                    .strip_prefix('\'')
+                    .and_then(|v| v.strip_suffix('\''))
+            })
+            .unwrap_or(expected_value);
+
+        return Some(evaluate_single_condition(
+            var_name,
+            operator,
+            expected_value,
+            brand_config,
+        ));
+    }
+
+    // Handle presence checks (var is set, or !var is not set)
+    let re = Regex::new(r"^\s*(!?)\s*([^\s=!()&|]+)\s*$").unwrap();
+    if let Some(caps) = re.captures(expression) {
+        let negated = caps.get(1).is_some_and(|m| !m.as_str().is_empty());
+        let var_name = caps.get(2).map_or("", |m| m.as_str());
+
+        return Some(resolve_condition_variable(var_name, brand_config).is_some() != negated);
+    }
+
+    // Invalid or unsupported expression format
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_namespaced_conditions() {
+        let mut strings = HashMap::new();
+        strings.insert(
+            "brandShortName".to_string(),
+            "Firefox Developer Edition".to_string(),
+        );
+        strings.insert("name".to_string(), "from-strings".to_string());
+        strings.insert("title".to_string(), "Développeur".to_string());
+        strings.insert("ops".to_string(), "A && B".to_string());
+
+        let mut env = HashMap::new();
+        env.insert("name".to_string(), "aurora".to_string());
+
+        let brand_config = BrandConfig {
+            strings,
+            env,
+            ..Default::default()
+        };
+
+        let cases = [
+            // Bare names keep resolving to env
+            ("name == aurora", true),
+            ("env.name == aurora", true),
+            ("strings.name == from-strings", true),
+            ("strings.name == aurora", false),
+            // Quoted values can contain spaces
+            (
+                "strings.brandShortName == \"Firefox Developer Edition\"",
+                true,
+            ),
+            ("strings.brandShortName != 'Firefox'", true),
+            // Presence checks
+            ("strings.brandShortName", true),
+            ("strings.brandFullName", false),
+            ("!strings.brandFullName", true),
+            ("!env.name", false),
+            ("env.name == aurora && !strings.missing", true),
+            // Non-ASCII text next to operators
+            ("strings.title == \"Développeur\" && name == aurora", true),
+            ("name == beta || strings.title != 'Développeur'", false),
+            // Operators inside quoted values don't split the condition
+            ("strings.ops == \"A && B\"", true),
+            ("strings.ops == 'A || B' || name == aurora", true),
+            (
+                "(name == beta || name == aurora) && strings.ops == \"A && B\"",
+                true,
+            ),
+        ];
+        for (expression, expected) in cases {
+            assert_eq!(
+                evaluate_condition_expression(expression, &brand_config),
+                expected,
+                "expression: {}",
+                expression
+            );
+        }
+    }
+
+    #[test]
+    fn test_check_condition_expression() {
+        for valid in ["name == b", "!env.x || (a != 'c d' && e)"] {
+            assert!(check_condition_expression(valid).is_ok(), "{}", valid);
+        }
+        // Parts after one that already decides the result are checked too
+        for invalid in ["name = b", "", "a || b = c", "a == b == c", "a &&"] {
+            assert!(check_condition_expression(invalid).is_err(), "{}", invalid);
+        }
+    }
+}
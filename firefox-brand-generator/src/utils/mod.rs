@@ -1 +1,5 @@
+pub mod builtins;
+pub mod conditions;
+pub mod mozilla_preprocessor;
 pub mod string_processing;
+pub mod syntax_check;
@@ -0,0 +1,533 @@
+//! A subset of mozilla-central's `preprocessor.py`, so upstream branding
+//! files can be used as `copy-preprocess` inputs without rewriting them.
+//!
+//! Supported directives: `#define`, `#undef`, `#ifdef`, `#ifndef`, `#if`,
+//! `#elif`, `#elifdef`, `#elifndef`, `#else`, `#endif`, `#expand`,
+//! `#literal`, `#include`, `#filter`, `#unfilter` and `#error`. Supported
+//! filters: `substitution`, `attemptSubstitution`, `emptyLines`,
+//! `slashslash` and `spaces`.
+
+use crate::config::types::BrandConfig;
+use crate::error::{Error, Result};
+use regex::{Captures, Regex};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Preprocess `content` (read from `path`) with the brand's strings and env
+/// as the initial definitions. Env values win when a name is in both.
+pub fn preprocess(path: &Path, content: &str, brand_config: &BrandConfig) -> Result<String> {
+    let mut definitions: HashMap<String, String> = brand_config.strings.clone();
+    definitions.extend(brand_config.env.clone());
+
+    let mut preprocessor = Preprocessor {
+        marker: marker_for(path),
+        definitions,
+        filters: HashSet::new(),
+        include_stack: Vec::new(),
+    };
+
+    let mut output = Vec::new();
+    preprocessor.process(path, content, &mut output)?;
+
+    let mut result = output.join("\n");
+    if content.ends_with('\n') && !result.is_empty() {
+        result.push('\n');
+    }
+    Ok(result)
+}
+
+/// CSS files use `%` as the directive marker upstream, since `#` starts selectors.
+fn marker_for(path: &Path) -> char {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("css") => '%',
+        _ => '#',
+    }
+}
+
+struct Preprocessor {
+    marker: char,
+    definitions: HashMap<String, String>,
+    filters: HashSet<String>,
+    include_stack: Vec<PathBuf>,
+}
+
+/// State of one `#if`/`#ifdef` block.
+struct Conditional {
+    /// Whether the enclosing block is emitting lines
+    parent_active: bool,
+    /// Whether the current branch is emitting lines
+    active: bool,
+    /// Whether any branch so far has been taken
+    taken: bool,
+}
+
+impl Preprocessor {
+    fn process(&mut self, path: &Path, content: &str, output: &mut Vec<String>) -> Result<()> {
+        let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        if self.include_stack.contains(&canonical) {
+            return Err(Error::Transformation(format!(
+                "Include cycle detected: {}",
+                path.display()
+            )));
+        }
+        self.include_stack.push(canonical);
+
+        // As upstream, the marker must start the line and directives are lowercase
+        let directive = Regex::new(&format!(
+            r"^{}([a-z]+)(?:\s+(.*?))?\s*$",
+            regex::escape(&self.marker.to_string())
+        ))
+        .unwrap();
+
+        let mut conditionals: Vec<Conditional> = Vec::new();
+
+        for (index, line) in content.lines().enumerate() {
+            let location = || format!("{}:{}", path.display(), index + 1);
+            let active = conditionals.last().is_none_or(|c| c.active);
+
+            let Some(caps) = directive.captures(line) else {
+                if active {
+                    self.emit(line, output)
+                        .map_err(|e| prefixed(&location(), e))?;
+                }
+                continue;
+            };
+
+            let command = caps.get(1).map_or("", |m| m.as_str());
+            let args = caps.get(2).map_or("", |m| m.as_str());
+
+            match command {
+                "ifdef" | "ifndef" | "if" => {
+                    let condition = active
+                        && match command {
+                            "ifdef" => self.definitions.contains_key(args),
+                            "ifndef" => !self.definitions.contains_key(args),
+                            _ => self.evaluate(args).map_err(|e| prefixed(&location(), e))?,
+                        };
+                    conditionals.push(Conditional {
+                        parent_active: active,
+                        active: condition,
+                        taken: condition,
+                    });
+                }
+                "elif" | "elifdef" | "elifndef" | "else" => {
+                    let Some(current) = conditionals.last() else {
+                        return Err(Error::Transformation(format!(
+                            "{}: {}{} without matching {}if",
+                            location(),
+                            self.marker,
+                            command,
+                            self.marker
+                        )));
+                    };
+                    let candidate = current.parent_active && !current.taken;
+                    let condition = candidate
+                        && match command {
+                            "elifdef" => self.definitions.contains_key(args),
+                            "elifndef" => !self.definitions.contains_key(args),
+                            "elif" => self.evaluate(args).map_err(|e| prefixed(&location(), e))?,
+                            _ => true,
+                        };
+                    let current = conditionals.last_mut().unwrap();
+                    current.active = condition;
+                    current.taken |= condition;
+                }
+                "endif" => {
+                    if conditionals.pop().is_none() {
+                        return Err(Error::Transformation(format!(
+                            "{}: {}endif without matching {}if",
+                            location(),
+                            self.marker,
+                            self.marker
+                        )));
+                    }
+                }
+                _ if !active => {}
+                "define" => {
+                    let mut parts = args.splitn(2, char::is_whitespace);
+                    let name = parts.next().unwrap_or("");
+                    let value = parts.next().map_or("", str::trim);
+                    self.definitions.insert(name.to_string(), value.to_string());
+                }
+                "undef" => {
+                    self.definitions.remove(args);
+                }
+                "expand" => {
+                    let expanded = self.expand(args);
+                    self.emit(&expanded, output)
+                        .map_err(|e| prefixed(&location(), e))?;
+                }
+                "literal" => output.push(args.to_string()),
+                "filter" => self
+                    .filters
+                    .extend(args.split_whitespace().map(str::to_string)),
+                "unfilter" => {
+                    for filter in args.split_whitespace() {
+                        self.filters.remove(filter);
+                    }
+                }
+                "include" => {
+                    let base = path.parent().unwrap_or(Path::new("."));
+                    let include_path = base.join(args);
+                    let included = fs::read_to_string(&include_path).map_err(|_| {
+                        Error::Transformation(format!(
+                            "{}: included file not found: {}",
+                            location(),
+                            include_path.display()
+                        ))
+                    })?;
+                    self.process(&include_path, &included, output)?;
+                }
+                "error" => {
+                    return Err(Error::Transformation(format!(
+                        "{}: {}error {}",
+                        location(),
+                        self.marker,
+                        args
+                    )));
+                }
+                _ => {
+                    return Err(Error::Transformation(format!(
+                        "{}: unknown directive '{}{}'",
+                        location(),
+                        self.marker,
+                        command
+                    )));
+                }
+            }
+        }
+
+        if !conditionals.is_empty() {
+            return Err(Error::Transformation(format!(
+                "{}: unterminated {}if block",
+                path.display(),
+                self.marker
+            )));
+        }
+
+        self.include_stack.pop();
+        Ok(())
+    }
+
+    /// Apply the active filters to a line and add it to the output.
+    fn emit(&self, line: &str, output: &mut Vec<String>) -> Result<()> {
+        let mut line = line.to_string();
+
+        if self.filters.contains("slashslash")
+            && let Some(index) = line.find("//")
+        {
+            line.truncate(index);
+            line.truncate(line.trim_end().len());
+        }
+        if self.filters.contains("spaces") {
+            let spaces = Regex::new(r" {2,}").unwrap();
+            line = spaces.replace_all(&line, " ").into_owned();
+        }
+        if self.filters.contains("substitution") {
+            line = self.substitute(&line, false)?;
+        } else if self.filters.contains("attemptSubstitution") {
+            line = self.substitute(&line, true)?;
+        }
+        if self.filters.contains("emptyLines") && line.trim().is_empty() {
+            return Ok(());
+        }
+
+        output.push(line);
+        Ok(())
+    }
+
+    /// Replace `@VAR@` references. Undefined names are an error unless `lenient`.
+    fn substitute(&self, line: &str, lenient: bool) -> Result<String> {
+        let reference = Regex::new(r"@(\w+)@").unwrap();
+        let mut missing = None;
+        let result = reference.replace_all(line, |caps: &Captures| {
+            let name = &caps[1];
+            match self.definitions.get(name) {
+                Some(value) => value.clone(),
+                None => {
+                    missing.get_or_insert_with(|| name.to_string());
+                    String::new()
+                }
+            }
+        });
+
+        match missing {
+            Some(name) if !lenient => Err(Error::Transformation(format!(
+                "undefined variable '{}' in substitution",
+                name
+            ))),
+            _ => Ok(result.into_owned()),
+        }
+    }
+
+    /// Replace `__VAR__` references, as `#expand` does. Undefined names become empty.
+    fn expand(&self, line: &str) -> String {
+        let reference = Regex::new(r"__(\w+?)__").unwrap();
+        reference
+            .replace_all(line, |caps: &Captures| {
+                self.definitions.get(&caps[1]).cloned().unwrap_or_default()
+            })
+            .into_owned()
+    }
+
+    fn evaluate(&self, expression: &str) -> Result<bool> {
+        let tokens = tokenize(expression)?;
+        let mut parser = ExpressionParser {
+            tokens: &tokens,
+            position: 0,
+            definitions: &self.definitions,
+        };
+        let value = parser.parse_or()?;
+        if parser.position != tokens.len() {
+            return Err(Error::Transformation(format!(
+                "unexpected '{}' in expression '{}'",
+                tokens[parser.position], expression
+            )));
+        }
+        Ok(value.is_truthy())
+    }
+}
+
+fn prefixed(location: &str, error: Error) -> Error {
+    match error {
+        Error::Transformation(message) => {
+            Error::Transformation(format!("{}: {}", location, message))
+        }
+        other => other,
+    }
+}
+
+fn tokenize(expression: &str) -> Result<Vec<String>> {
+    let token = Regex::new(r#"\s*(==|!=|&&|\|\||[()!]|"[^"]*"|'[^']*'|[\w.-]+)"#).unwrap();
+    let mut tokens = Vec::new();
+    let mut rest = expression;
+
+    while !rest.trim().is_empty() {
+        let caps = token
+            .captures(rest)
+            .filter(|caps| caps.get(0).unwrap().start() == 0)
+            .ok_or_else(|| Error::Transformation(format!("invalid expression '{}'", expression)))?;
+        tokens.push(caps[1].to_string());
+        rest = &rest[caps.get(0).unwrap().end()..];
+    }
+
+    Ok(tokens)
+}
+
+enum ExpressionValue {
+    Bool(bool),
+    Number(i64),
+    Text(String),
+}
+
+impl ExpressionValue {
+    fn is_truthy(&self) -> bool {
+        match self {
+            Self::Bool(value) => *value,
+            Self::Number(value) => *value != 0,
+            Self::Text(value) => !value.is_empty(),
+        }
+    }
+
+    fn as_text(&self) -> String {
+        match self {
+            Self::Bool(value) => (*value as i64).to_string(),
+            Self::Number(value) => value.to_string(),
+            Self::Text(value) => value.clone(),
+        }
+    }
+}
+
+/// Recursive-descent evaluator for `#if` expressions. As upstream, a name
+/// that isn't defined evaluates to itself, so `#if NAME == NAME` holds.
+struct ExpressionParser<'a> {
+    tokens: &'a [String],
+    position: usize,
+    definitions: &'a HashMap<String, String>,
+}
+
+impl ExpressionParser<'_> {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.position).map(String::as_str)
+    }
+
+    fn next(&mut self) -> Result<&str> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .ok_or_else(|| Error::Transformation("unexpected end of expression".to_string()))?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<()> {
+        let token = self.next()?;
+        if token != expected {
+            return Err(Error::Transformation(format!(
+                "expected '{}' but found '{}'",
+                expected, token
+            )));
+        }
+        Ok(())
+    }
+
+    fn parse_or(&mut self) -> Result<ExpressionValue> {
+        let mut value = self.parse_and()?;
+        while self.peek() == Some("||") {
+            self.position += 1;
+            let right = self.parse_and()?;
+            value = ExpressionValue::Bool(value.is_truthy() || right.is_truthy());
+        }
+        Ok(value)
+    }
+
+    fn parse_and(&mut self) -> Result<ExpressionValue> {
+        let mut value = self.parse_unary()?;
+        while self.peek() == Some("&&") {
+            self.position += 1;
+            let right = self.parse_unary()?;
+            value = ExpressionValue::Bool(value.is_truthy() && right.is_truthy());
+        }
+        Ok(value)
+    }
+
+    fn parse_unary(&mut self) -> Result<ExpressionValue> {
+        if self.peek() == Some("!") {
+            self.position += 1;
+            let value = self.parse_unary()?;
+            return Ok(ExpressionValue::Bool(!value.is_truthy()));
+        }
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> Result<ExpressionValue> {
+        let left = self.parse_primary()?;
+        match self.peek() {
+            Some(operator @ ("==" | "!=")) => {
+                let equal = operator == "==";
+                self.position += 1;
+                let right = self.parse_primary()?;
+                Ok(ExpressionValue::Bool(
+                    (left.as_text() == right.as_text()) == equal,
+                ))
+            }
+            _ => Ok(left),
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<ExpressionValue> {
+        let token = self.next()?.to_string();
+        match token.as_str() {
+            "(" => {
+                let value = self.parse_or()?;
+                self.expect(")")?;
+                Ok(value)
+            }
+            "defined" => {
+                self.expect("(")?;
+                let name = self.next()?.to_string();
+                self.expect(")")?;
+                Ok(ExpressionValue::Bool(self.definitions.contains_key(&name)))
+            }
+            _ if token.len() >= 2
+                && ((token.starts_with('"') && token.ends_with('"'))
+                    || (token.starts_with('\'') && token.ends_with('\''))) =>
+            {
+                Ok(ExpressionValue::Text(token[1..token.len() - 1].to_string()))
+            }
+            _ => {
+                let value = self.definitions.get(&token).cloned().unwrap_or(token);
+                Ok(match value.parse::<i64>() {
+                    Ok(number) => ExpressionValue::Number(number),
+                    Err(_) => ExpressionValue::Text(value),
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn brand_config() -> BrandConfig {
+        let mut strings = HashMap::new();
+        strings.insert("brandShortName".to_string(), "Nightly".to_string());
+
+        let mut env = HashMap::new();
+        env.insert("name".to_string(), "nightly".to_string());
+        env.insert("MOZ_UPDATE_CHANNEL".to_string(), "nightly".to_string());
+        env.insert("NIGHTLY_BUILD".to_string(), "1".to_string());
+
+        BrandConfig {
+            strings,
+            env,
+            ..Default::default()
+        }
+    }
+
+    fn run(content: &str) -> Result<String> {
+        preprocess(Path::new("brand.ftl"), content, &brand_config())
+    }
+
+    #[test]
+    fn test_conditionals() {
+        let input = "a\n#ifdef NIGHTLY_BUILD\nnightly\n#else\nrelease\n#endif\nb\n";
+        assert_eq!(run(input).unwrap(), "a\nnightly\nb\n");
+
+        let input = "#ifndef NIGHTLY_BUILD\nno\n#elifdef MOZ_UPDATE_CHANNEL\nyes\n#endif\n";
+        assert_eq!(run(input).unwrap(), "yes\n");
+
+        let input = "#if MOZ_UPDATE_CHANNEL == beta\n1\n#elif defined(NIGHTLY_BUILD) && !defined(RELEASE)\n2\n#else\n3\n#endif\n";
+        assert_eq!(run(input).unwrap(), "2\n");
+
+        // Nested blocks inside an untaken branch stay off
+        let input = "#if 0\n#ifdef NIGHTLY_BUILD\nhidden\n#endif\n#else\nshown\n#endif\n";
+        assert_eq!(run(input).unwrap(), "shown\n");
+
+        // Defines are visible to later conditionals
+        let input = "#define EXTRA\n#ifdef EXTRA\nextra\n#endif\n#undef EXTRA\n#ifdef EXTRA\ngone\n#endif\n";
+        assert_eq!(run(input).unwrap(), "extra\n");
+
+        // Indented or mixed-case directives are plain text, as upstream
+        let input = "  #ifdef RELEASE\nkept\n  #endif\n#IfDef RELEASE\n";
+        assert_eq!(run(input).unwrap(), input);
+    }
+
+    #[test]
+    fn test_substitution_and_expand() {
+        let input = "#filter substitution\n-brand-short-name = @brandShortName@\n#unfilter substitution\nkept @as-is@\n#expand channel = __MOZ_UPDATE_CHANNEL__\n";
+        assert_eq!(
+            run(input).unwrap(),
+            "-brand-short-name = Nightly\nkept @as-is@\nchannel = nightly\n"
+        );
+
+        // Undefined names are an error under substitution, but not attemptSubstitution
+        assert!(run("#filter substitution\n@missing@\n").is_err());
+        let input = "#filter attemptSubstitution emptyLines\n[@missing@]\n\nx\n";
+        assert_eq!(run(input).unwrap(), "[]\nx\n");
+
+        // A bare #define is defined but empty
+        let input = "#define EXTRA\n#expand [__EXTRA__]\n";
+        assert_eq!(run(input).unwrap(), "[]\n");
+    }
+
+    #[test]
+    fn test_comments_and_errors() {
+        // Fluent comments aren't directives
+        let input = "# comment\n## group comment\nkey = value\n";
+        assert_eq!(run(input).unwrap(), input);
+
+        // CSS files use % as the marker
+        let input = "%ifdef NIGHTLY_BUILD\n#nav-bar { color: red; }\n%endif\n";
+        let result = preprocess(Path::new("style.css"), input, &brand_config()).unwrap();
+        assert_eq!(result, "#nav-bar { color: red; }\n");
+
+        assert!(run("#bogus\n").is_err());
+        assert!(run("#ifdef NIGHTLY_BUILD\n").is_err());
+        assert!(run("#endif\n").is_err());
+        assert!(run("#error stop here\n").is_err());
+        assert!(run("#if 0\n#error not reached\n#endif\n").is_ok());
+    }
+}
@@ -1,31 +1,259 @@
 use crate::config::types::BrandConfig;
-use crate::error::Result;
-use regex::{Captures, Regex};
+use crate::error::{Error, Result};
+use regex::Regex;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::path::Path;
+
+/// An escaping or case filter applied to a `{{#str key | filter}}` value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StringFilter {
+    /// Leave the value untouched, bypassing any default escape
+    Raw,
+    Xml,
+    Nsis,
+    Properties,
+    Fluent,
+    Json,
+    Upper,
+    Lower,
+}
+
+impl StringFilter {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "raw" => Some(Self::Raw),
+            "xml" => Some(Self::Xml),
+            "nsis" => Some(Self::Nsis),
+            "properties" => Some(Self::Properties),
+            "fluent" => Some(Self::Fluent),
+            "json" => Some(Self::Json),
+            "upper" => Some(Self::Upper),
+            "lower" => Some(Self::Lower),
+            _ => None,
+        }
+    }
+
+    /// The escape that suits a file, judged by its extension.
+    pub fn for_output_path(path: &Path) -> Option<Self> {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|s| s.to_lowercase());
+
+        match extension.as_deref() {
+            Some("xml") | Some("manifest") | Some("plist") | Some("svg") | Some("xhtml") => {
+                Some(Self::Xml)
+            }
+            Some("nsi") | Some("nsh") => Some(Self::Nsis),
+            Some("properties") => Some(Self::Properties),
+            Some("ftl") => Some(Self::Fluent),
+            Some("json") => Some(Self::Json),
+            _ => None,
+        }
+    }
+
+    /// Whether this filter escapes for a target language, as opposed to changing case.
+    fn is_escape(&self) -> bool {
+        !matches!(self, Self::Upper | Self::Lower)
+    }
+
+    pub fn apply(&self, value: &str) -> String {
+        match self {
+            Self::Raw => value.to_string(),
+            Self::Xml => escape_chars(value, |c| match c {
+                '&' => Some("&amp;"),
+                '<' => Some("&lt;"),
+                '>' => Some("&gt;"),
+                '"' => Some("&quot;"),
+                '\'' => Some("&apos;"),
+                _ => None,
+            }),
+            // Escapes for use inside a double-quoted NSIS string
+            Self::Nsis => escape_chars(value, |c| match c {
+                '$' => Some("$$"),
+                '"' => Some("$\\\""),
+                '\n' => Some("$\\n"),
+                '\r' => Some("$\\r"),
+                '\t' => Some("$\\t"),
+                _ => None,
+            }),
+            Self::Properties => {
+                let escaped = escape_chars(value, |c| match c {
+                    '\\' => Some("\\\\"),
+                    '\n' => Some("\\n"),
+                    '\r' => Some("\\r"),
+                    '\t' => Some("\\t"),
+                    _ => None,
+                });
+                // Leading whitespace would otherwise be dropped by the parser
+                match escaped.strip_prefix(' ') {
+                    Some(rest) => format!("\\ {}", rest),
+                    None => escaped,
+                }
+            }
+            // Braces start placeables in Fluent, so emit them as string literals
+            Self::Fluent => escape_chars(value, |c| match c {
+                '{' => Some("{ \"{\" }"),
+                '}' => Some("{ \"}\" }"),
+                _ => None,
+            }),
+            Self::Json => {
+                let quoted = serde_json::to_string(value).unwrap_or_default();
+                quoted[1..quoted.len() - 1].to_string()
+            }
+            Self::Upper => value.to_uppercase(),
+            Self::Lower => value.to_lowercase(),
+        }
+    }
+}
+
+fn escape_chars(value: &str, replacement: impl Fn(char) -> Option<&'static str>) -> String {
+    let mut result = String::with_capacity(value.len());
+    for c in value.chars() {
+        match replacement(c) {
+            Some(escaped) => result.push_str(escaped),
+            None => result.push(c),
+        }
+    }
+    result
+}
 
 /// Process string replacements in the given content using the brand configuration.
 /// This function replaces occurrences of {{#str key}} with the corresponding value
 /// from the brand_config.strings map.
 pub fn process_string_replacements(content: &str, brand_config: &BrandConfig) -> Result<String> {
-    // Create the regex pattern
-    let str_regex = Regex::new(r"\{\{#str\s+([^\s\}]+)\}\}").unwrap();
-
-    // Process each match with proper lifetimes in closure
-    let result = str_regex
-        .replace_all(content, |caps: &Captures| {
-            // Extract the key
-            let key = caps.get(1).map_or("", |m| m.as_str());
-
-            // Look up the key in the strings map
-            match brand_config.strings.get(key) {
-                Some(value) => value.to_string(),
-                None => {
-                    // If the key doesn't exist, leave the placeholder unchanged
-                    caps.get(0).map_or("", |m| m.as_str()).to_string()
-                }
+    process_string_replacements_with_escape(content, brand_config, None)
+}
+
+/// Expand a template field and read the result as `T`, e.g. `[u32; 2]`.
+///
+/// A field that is exactly `{{#str key}}` uses the key's typed value when the
+/// brand config has one (`"dmgWindowSize": [680, 425]`). Otherwise the expanded
+/// text is read word by word, so `"680 425"` gives the same result.
+pub fn process_typed<T: DeserializeOwned>(template: &str, brand_config: &BrandConfig) -> Result<T> {
+    let whole_placeholder = Regex::new(r"^\s*\{\{#str\s+([^\s\}\|]+)\s*\}\}\s*$").unwrap();
+    let typed = whole_placeholder
+        .captures(template)
+        .and_then(|caps| brand_config.data.get(&caps[1]).cloned());
+
+    let value = match typed {
+        Some(value) => value,
+        None => {
+            let text = process_string_replacements(template, brand_config)?;
+            let mut words: Vec<Value> = text
+                .split_whitespace()
+                .map(|word| {
+                    serde_json::from_str(word).unwrap_or_else(|_| Value::String(word.to_string()))
+                })
+                .collect();
+            if words.len() == 1 {
+                words.remove(0)
+            } else {
+                Value::Array(words)
             }
-        })
-        .to_string();
+        }
+    };
+
+    serde_json::from_value(value.clone()).map_err(|e| {
+        Error::Config(format!(
+            "'{}' resolves to {}, which is not a valid {}: {}",
+            template,
+            value,
+            std::any::type_name::<T>(),
+            e
+        ))
+    })
+}
+
+/// Expand `{{#str key}}` and `{{#env key}}` in a path field. Unlike file
+/// contents, a placeholder naming a missing key is an error rather than left
+/// in place, since it would otherwise end up in a file name.
+pub fn expand_path_placeholders(template: &str, brand_config: &BrandConfig) -> Result<String> {
+    let placeholder = Regex::new(r"\{\{#(str|env)\s+([^\s\}]+)\s*\}\}").unwrap();
+
+    let mut result = String::with_capacity(template.len());
+    let mut last_end = 0;
+    for caps in placeholder.captures_iter(template) {
+        let whole = caps.get(0).unwrap();
+        result.push_str(&template[last_end..whole.start()]);
+        last_end = whole.end();
 
+        let (section, values) = match &caps[1] {
+            "str" => ("strings", &brand_config.strings),
+            _ => ("env", &brand_config.env),
+        };
+        let value = values.get(&caps[2]).ok_or_else(|| {
+            Error::MissingBrandConfigValue(format!("{}.{} (in '{}')", section, &caps[2], template))
+        })?;
+        result.push_str(value);
+    }
+
+    result.push_str(&template[last_end..]);
+    Ok(result)
+}
+
+/// Like [`process_string_replacements`], but also applies `{{#str key | filter}}`
+/// filters. `default_escape` is applied to placeholders that don't name an
+/// escape of their own; `| raw` opts a single placeholder out of it.
+pub fn process_string_replacements_with_escape(
+    content: &str,
+    brand_config: &BrandConfig,
+    default_escape: Option<StringFilter>,
+) -> Result<String> {
+    // Create the regex pattern
+    let str_regex =
+        Regex::new(r"\{\{#str\s+([^\s\}\|]+)((?:\s*\|\s*[A-Za-z0-9_-]+)*)\s*\}\}").unwrap();
+
+    let mut result = String::with_capacity(content.len());
+    let mut last_end = 0;
+
+    for caps in str_regex.captures_iter(content) {
+        let whole = caps.get(0).unwrap();
+        result.push_str(&content[last_end..whole.start()]);
+        last_end = whole.end();
+
+        // Extract the key
+        let key = caps.get(1).map_or("", |m| m.as_str());
+
+        let filters = caps
+            .get(2)
+            .map_or("", |m| m.as_str())
+            .split('|')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                StringFilter::from_name(name).ok_or_else(|| {
+                    Error::Transformation(format!(
+                        "Unknown filter '{}' in '{}'",
+                        name,
+                        whole.as_str()
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // Look up the key in the strings map
+        let Some(value) = brand_config.strings.get(key) else {
+            // If the key doesn't exist, leave the placeholder unchanged
+            result.push_str(whole.as_str());
+            continue;
+        };
+
+        let mut value = value.to_string();
+        for filter in &filters {
+            value = filter.apply(&value);
+        }
+        // Escape last so case filters can't corrupt escape sequences
+        if !filters.iter().any(StringFilter::is_escape)
+            && let Some(escape) = default_escape
+        {
+            value = escape.apply(&value);
+        }
+        result.push_str(&value);
+    }
+
+    result.push_str(&content[last_end..]);
     Ok(result)
 }
 
@@ -43,6 +271,7 @@ mod tests {
         let brand_config = BrandConfig {
             strings,
             env: HashMap::new(),
+            ..Default::default()
         };
 
         let input = "Welcome to {{#str app_name}} version {{#str version}}!";
@@ -61,6 +290,7 @@ mod tests {
         let brand_config = BrandConfig {
             strings,
             env: HashMap::new(),
+            ..Default::default()
         };
 
         let input = "{{#str appName}}.app";
@@ -75,6 +305,7 @@ mod tests {
         let brand_config = BrandConfig {
             strings: HashMap::new(),
             env: HashMap::new(),
+            ..Default::default()
         };
 
         let input = "{{#str missing_key}}";
@@ -83,4 +314,126 @@ mod tests {
         let result = process_string_replacements(input, &brand_config).unwrap();
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn test_string_replacement_filters() {
+        let mut strings = HashMap::new();
+        strings.insert("name".to_string(), "Fire & \"Fox\" {1}".to_string());
+
+        let brand_config = BrandConfig {
+            strings,
+            env: HashMap::new(),
+            ..Default::default()
+        };
+
+        let cases = [
+            ("{{#str name | xml}}", "Fire &amp; &quot;Fox&quot; {1}"),
+            ("{{#str name | nsis}}", "Fire & $\\\"Fox$\\\" {1}"),
+            (
+                "{{#str name | fluent}}",
+                "Fire & \"Fox\" { \"{\" }1{ \"}\" }",
+            ),
+            ("{{#str name | json}}", "Fire & \\\"Fox\\\" {1}"),
+            ("{{#str name | upper}}", "FIRE & \"FOX\" {1}"),
+            ("{{#str name|lower|xml}}", "fire &amp; &quot;fox&quot; {1}"),
+        ];
+        for (input, expected) in cases {
+            let result = process_string_replacements(input, &brand_config).unwrap();
+            assert_eq!(result, expected, "input: {}", input);
+        }
+
+        // Unknown filters are reported
+        assert!(process_string_replacements("{{#str name | bogus}}", &brand_config).is_err());
+
+        // The default escape applies after case filters, unless the placeholder names its own
+        let default = Some(StringFilter::Xml);
+        let result = process_string_replacements_with_escape(
+            "{{#str name | upper}}",
+            &brand_config,
+            default,
+        )
+        .unwrap();
+        assert_eq!(result, "FIRE &amp; &quot;FOX&quot; {1}");
+        let result =
+            process_string_replacements_with_escape("{{#str name | raw}}", &brand_config, default)
+                .unwrap();
+        assert_eq!(result, "Fire & \"Fox\" {1}");
+        let result =
+            process_string_replacements_with_escape("{{#str name | json}}", &brand_config, default)
+                .unwrap();
+        assert_eq!(result, "Fire & \\\"Fox\\\" {1}");
+    }
+
+    #[test]
+    fn test_properties_filter() {
+        assert_eq!(StringFilter::Properties.apply(" a\\b\nc"), "\\ a\\\\b\\nc");
+        assert_eq!(
+            StringFilter::for_output_path(Path::new("locales/en-US/brand.properties")),
+            Some(StringFilter::Properties)
+        );
+        assert_eq!(StringFilter::for_output_path(Path::new("moz.build")), None);
+    }
+
+    #[test]
+    fn test_process_typed() {
+        let brand_config: BrandConfig = serde_json::from_value(serde_json::json!({
+            "strings": {
+                "dmgWindowSize": [680, 425],
+                "dmgWindowPosition": "200 120",
+                "iconSize": 128,
+                "brandShortName": "Firefox"
+            }
+        }))
+        .unwrap();
+
+        // Typed values still print as text
+        assert_eq!(brand_config.strings["dmgWindowSize"], "680 425");
+        assert_eq!(brand_config.strings["iconSize"], "128");
+
+        let size: [u32; 2] = process_typed("{{#str dmgWindowSize}}", &brand_config).unwrap();
+        assert_eq!(size, [680, 425]);
+        let position: [u32; 2] =
+            process_typed("{{#str dmgWindowPosition}}", &brand_config).unwrap();
+        assert_eq!(position, [200, 120]);
+        let icon_size: u32 = process_typed("{{#str iconSize}}", &brand_config).unwrap();
+        assert_eq!(icon_size, 128);
+
+        let err = process_typed::<[u32; 2]>("{{#str brandShortName}}", &brand_config).unwrap_err();
+        assert!(
+            err.to_string().contains("resolves to \"Firefox\""),
+            "{}",
+            err
+        );
+        assert!(process_typed::<[u32; 2]>("{{#str iconSize}}", &brand_config).is_err());
+    }
+
+    #[test]
+    fn test_expand_path_placeholders() {
+        let brand_config = BrandConfig {
+            strings: HashMap::from([("appName".to_string(), "Firefox Nightly".to_string())]),
+            env: HashMap::from([("channel".to_string(), "nightly".to_string())]),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            expand_path_placeholders(
+                "{{#str appName}}.app/icon-{{#env channel}}.png",
+                &brand_config
+            )
+            .unwrap(),
+            "Firefox Nightly.app/icon-nightly.png"
+        );
+        assert_eq!(
+            expand_path_placeholders("content/about.png", &brand_config).unwrap(),
+            "content/about.png"
+        );
+
+        let err = expand_path_placeholders("icon-{{#env missing}}.png", &brand_config).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("env.missing (in 'icon-{{#env missing}}.png')"),
+            "{}",
+            err
+        );
+    }
 }
@@ -0,0 +1,167 @@
+use crate::error::{Error, Result};
+use std::path::Path;
+
+/// Check that generated content parses as the format its extension implies.
+///
+/// `.ftl` files are parsed as Fluent and `.properties` files as Java
+/// properties; other files are accepted as-is. Every syntax error is
+/// reported with its line number.
+pub fn check_output_syntax(output_path: &Path, content: &str) -> Result<()> {
+    let extension = output_path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|s| s.to_lowercase());
+
+    let errors = match extension.as_deref() {
+        Some("ftl") => fluent_errors(content),
+        Some("properties") => properties_errors(content),
+        _ => Vec::new(),
+    };
+
+    if errors.is_empty() {
+        return Ok(());
+    }
+
+    let details: Vec<String> = errors
+        .iter()
+        .map(|(line, message)| format!("line {}: {}", line, message))
+        .collect();
+    Err(Error::Transformation(format!(
+        "Generated '{}' has syntax errors: {}",
+        output_path.display(),
+        details.join("; ")
+    )))
+}
+
+fn fluent_errors(content: &str) -> Vec<(usize, String)> {
+    match fluent_syntax::parser::parse(content) {
+        Ok(_) => Vec::new(),
+        Err((_, errors)) => errors
+            .iter()
+            .map(|error| (line_at(content, error.pos.start), error.kind.to_string()))
+            .collect(),
+    }
+}
+
+/// Errors the l10n tooling would report for a `.properties` file: malformed
+/// `\uXXXX` escapes and a line continuation with nothing after it.
+fn properties_errors(content: &str) -> Vec<(usize, String)> {
+    let mut errors = Vec::new();
+    let lines: Vec<&str> = content.lines().collect();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let start_line = i + 1;
+        let trimmed = lines[i].trim_start();
+
+        if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with('!') {
+            i += 1;
+            continue;
+        }
+
+        // Join continuation lines into one logical entry
+        let mut entry = String::new();
+        let mut line = trimmed;
+        loop {
+            match strip_continuation(line) {
+                Some(body) => {
+                    entry.push_str(body);
+                    i += 1;
+                    match lines.get(i) {
+                        Some(next) => line = next.trim_start(),
+                        None => {
+                            errors
+                                .push((start_line, "line continuation at end of file".to_string()));
+                            break;
+                        }
+                    }
+                }
+                None => {
+                    entry.push_str(line);
+                    i += 1;
+                    break;
+                }
+            }
+        }
+
+        if let Some(message) = check_properties_entry(&entry) {
+            errors.push((start_line, message));
+        }
+    }
+
+    errors
+}
+
+/// The line without its trailing continuation backslash, if it has one.
+fn strip_continuation(line: &str) -> Option<&str> {
+    let trailing = line.len() - line.trim_end_matches('\\').len();
+    (trailing % 2 == 1).then(|| &line[..line.len() - 1])
+}
+
+/// The first malformed `\uXXXX` escape in a logical entry, if any.
+fn check_properties_entry(entry: &str) -> Option<String> {
+    let mut chars = entry.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\\' && chars.next() == Some('u') {
+            let digits: String = chars.clone().take(4).collect();
+            if digits.len() < 4 || !digits.chars().all(|d| d.is_ascii_hexdigit()) {
+                return Some(format!("malformed \\uXXXX escape '\\u{}'", digits));
+            }
+            chars.nth(3);
+        }
+    }
+    None
+}
+
+fn line_at(content: &str, offset: usize) -> usize {
+    content[..offset.min(content.len())].matches('\n').count() + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fluent_syntax() {
+        let path = Path::new("locales/en-US/brand.ftl");
+
+        let valid = "# Comment\n-brand-short-name = Firefox\ntrademarkInfo = { \" \" }\n";
+        assert!(check_output_syntax(path, valid).is_ok());
+
+        let invalid = "-brand-short-name = Firefox\n-brand-full-name Firefox\n";
+        let err = check_output_syntax(path, invalid).unwrap_err().to_string();
+        assert!(err.contains("line 2"), "{}", err);
+    }
+
+    #[test]
+    fn test_properties_syntax() {
+        let path = Path::new("locales/en-US/brand.properties");
+
+        let valid = "# Comment\n! Also a comment\n\nbrandShortName=Firefox\nhomePageSingleStartMain = Firefox \\u00e9\nmulti=first \\\n  second\nescaped=ends with backslash \\\\\n";
+        assert!(check_output_syntax(path, valid).is_ok());
+
+        // Whitespace separators and keys without a value
+        let valid = "brandShortName Firefox\nbrandFullName\nescaped\\ key\tvalue\n";
+        assert!(check_output_syntax(path, valid).is_ok());
+
+        let err = check_output_syntax(path, "a=\\u00zz\n")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("line 1: malformed"), "{}", err);
+
+        let err = check_output_syntax(path, "a=b\nc=d \\")
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("line 2: line continuation at end of file"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_other_files_are_not_checked() {
+        assert!(check_output_syntax(Path::new("moz.build"), "{{{").is_ok());
+    }
+}